@@ -2,5 +2,6 @@
 pub enum AppState {
     MainMenu,
     SetMenu,
-    InGame
+    InGame,
+    GameOver,
 }
\ No newline at end of file
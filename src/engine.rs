@@ -0,0 +1,27 @@
+pub use board::{Position, Piece, CastlingRights, FigureType, ChessColor, Undo};
+pub use moves::Move;
+pub use movegen::{get_legalmoves, has_legal_move, legal_moves, is_square_attacked};
+
+pub const GRID: i32 = 8;
+
+mod board;
+mod bitboard;
+mod movegen;
+mod moves;
+mod zobrist;
+pub mod fen;
+pub mod notation;
+pub mod perft;
+pub mod pgn;
+pub mod search;
+pub mod epd;
+pub mod uci;
+
+pub fn is_on_board(square: (i32, i32)) -> bool {
+    square.0 >= 0 && square.0 < GRID && square.1 >= 0 && square.1 < GRID
+}
+
+// (4, 1) -> "e2"
+pub fn square_name(square: (i32, i32)) -> String {
+    format!("{}{}", (b'a' + square.0 as u8) as char, square.1 + 1)
+}
@@ -0,0 +1,190 @@
+use super::{Position, FigureType, ChessColor};
+
+// One bit per square, bit index is row * 8 + col so a1 is bit 0 and h8 is bit 63.
+pub type Bitboard = u64;
+
+const KNIGHT_OFFSETS: [(i32, i32); 8] = [(2, 1), (2, -1), (-2, 1), (-2, -1), (1, 2), (-1, 2), (1, -2), (-1, -2)];
+const KING_OFFSETS: [(i32, i32); 8] = [(1, 1), (1, 0), (1, -1), (0, 1), (0, -1), (-1, 1), (-1, 0), (-1, -1)];
+
+// the first four point towards higher bit indexes, the last four towards lower ones
+const DIRECTIONS: [(i32, i32); 8] = [(0, 1), (1, 1), (1, 0), (-1, 1), (0, -1), (-1, -1), (-1, 0), (1, -1)];
+const STRAIGHT: [usize; 4] = [0, 2, 4, 6];
+const DIAGONAL: [usize; 4] = [1, 3, 5, 7];
+
+const KNIGHT_ATTACKS: [Bitboard; 64] = leaper_table(&KNIGHT_OFFSETS);
+const KING_ATTACKS: [Bitboard; 64] = leaper_table(&KING_OFFSETS);
+const PAWN_ATTACKS: [[Bitboard; 64]; 2] = [
+    leaper_table(&[(-1, 1), (1, 1)]),
+    leaper_table(&[(-1, -1), (1, -1)]),
+];
+const RAYS: [[Bitboard; 64]; 8] = ray_table();
+
+pub fn square_bit(square: (i32, i32)) -> Bitboard {
+    1 << (square.1 * 8 + square.0)
+}
+
+pub fn to_square(index: u32) -> (i32, i32) {
+    ((index % 8) as i32, (index / 8) as i32)
+}
+
+// iterates the set bits as (col, row) squares
+pub fn squares(mut bitboard: Bitboard) -> impl Iterator<Item = (i32, i32)> {
+    std::iter::from_fn(move || {
+        if bitboard == 0 {
+            return None;
+        }
+        let index = bitboard.trailing_zeros();
+        bitboard &= bitboard - 1;
+        Some(to_square(index))
+    })
+}
+
+pub fn color_index(color: ChessColor) -> usize {
+    match color {
+        ChessColor::White => 0,
+        ChessColor::Black => 1,
+    }
+}
+
+pub fn figure_index(figure_type: FigureType) -> usize {
+    match figure_type {
+        FigureType::Pawn => 0,
+        FigureType::Knight => 1,
+        FigureType::Bishop => 2,
+        FigureType::Fort => 3,
+        FigureType::Queen => 4,
+        FigureType::King => 5,
+    }
+}
+
+pub fn knight_attacks(square: (i32, i32)) -> Bitboard {
+    KNIGHT_ATTACKS[(square.1 * 8 + square.0) as usize]
+}
+
+pub fn king_attacks(square: (i32, i32)) -> Bitboard {
+    KING_ATTACKS[(square.1 * 8 + square.0) as usize]
+}
+
+// squares a pawn of `color` standing on `square` would capture on
+pub fn pawn_attacks(square: (i32, i32), color: ChessColor) -> Bitboard {
+    PAWN_ATTACKS[color_index(color)][(square.1 * 8 + square.0) as usize]
+}
+
+pub fn rook_attacks(square: (i32, i32), occupied: Bitboard) -> Bitboard {
+    STRAIGHT.iter().fold(0, |attacks, dir| attacks | ray_attacks(*dir, square, occupied))
+}
+
+pub fn bishop_attacks(square: (i32, i32), occupied: Bitboard) -> Bitboard {
+    DIAGONAL.iter().fold(0, |attacks, dir| attacks | ray_attacks(*dir, square, occupied))
+}
+
+// classical approach: take the whole ray and cut it off behind the first blocker
+fn ray_attacks(dir: usize, square: (i32, i32), occupied: Bitboard) -> Bitboard {
+    let ray = RAYS[dir][(square.1 * 8 + square.0) as usize];
+    let blockers = ray & occupied;
+    if blockers == 0 {
+        return ray;
+    }
+    let first_blocker = if dir < 4 {
+        blockers.trailing_zeros()
+    } else {
+        63 - blockers.leading_zeros()
+    };
+    ray ^ RAYS[dir][first_blocker as usize]
+}
+
+pub fn is_square_attacked(
+    position: &Position,
+    square: (i32, i32),
+    by: ChessColor,
+) -> bool {
+    attackers(position, square, by, position.occupied()) != 0
+}
+
+// the figures of `by` attacking `square`, sliders are blocked by `occupied` only
+pub fn attackers(
+    position: &Position,
+    square: (i32, i32),
+    by: ChessColor,
+    occupied: Bitboard,
+) -> Bitboard {
+    let queens = position.bitboard(FigureType::Queen, by);
+
+    // a pawn of ours on the square would attack exactly the squares their pawns attack it from
+    pawn_attacks(square, by.opposite()) & position.bitboard(FigureType::Pawn, by)
+        | knight_attacks(square) & position.bitboard(FigureType::Knight, by)
+        | king_attacks(square) & position.bitboard(FigureType::King, by)
+        | rook_attacks(square, occupied) & (position.bitboard(FigureType::Fort, by) | queens)
+        | bishop_attacks(square, occupied) & (position.bitboard(FigureType::Bishop, by) | queens)
+}
+
+// the squares from `square` up to and including `target` if they share a line with nothing in between
+pub fn line_to(square: (i32, i32), target: Bitboard, occupied: Bitboard) -> Bitboard {
+    (0..8)
+        .map(|dir| ray_attacks(dir, square, occupied))
+        .find(|line| line & target != 0)
+        .unwrap_or(0)
+}
+
+// Figures of `color` pinned to the king on `king`, each with the line it may still move on:
+// the squares between the king and the pinning slider, the slider included.
+pub fn pins(position: &Position, king: (i32, i32), color: ChessColor) -> Vec<(Bitboard, Bitboard)> {
+    let occupied = position.occupied();
+    let own = position.color_bitboard(color);
+    let enemy = color.opposite();
+    let queens = position.bitboard(FigureType::Queen, enemy);
+    let mut pins = Vec::new();
+    for dir in 0..8 {
+        let blocker = ray_attacks(dir, king, occupied) & own;
+        if blocker == 0 {
+            continue;
+        }
+        let line = ray_attacks(dir, king, occupied & !blocker);
+        let sliders = if STRAIGHT.contains(&dir) {
+            position.bitboard(FigureType::Fort, enemy) | queens
+        } else {
+            position.bitboard(FigureType::Bishop, enemy) | queens
+        };
+        if line & sliders != 0 {
+            pins.push((blocker, line));
+        }
+    }
+    pins
+}
+
+const fn leaper_table(offsets: &[(i32, i32)]) -> [Bitboard; 64] {
+    let mut table = [0; 64];
+    let mut index = 0;
+    while index < 64 {
+        let (col, row) = ((index % 8) as i32, (index / 8) as i32);
+        let mut offset = 0;
+        while offset < offsets.len() {
+            let (target_col, target_row) = (col + offsets[offset].0, row + offsets[offset].1);
+            if target_col >= 0 && target_col < 8 && target_row >= 0 && target_row < 8 {
+                table[index] |= 1 << (target_row * 8 + target_col);
+            }
+            offset += 1;
+        }
+        index += 1;
+    }
+    table
+}
+
+const fn ray_table() -> [[Bitboard; 64]; 8] {
+    let mut table = [[0; 64]; 8];
+    let mut dir = 0;
+    while dir < 8 {
+        let mut index = 0;
+        while index < 64 {
+            let (mut col, mut row) = ((index % 8) as i32 + DIRECTIONS[dir].0, (index / 8) as i32 + DIRECTIONS[dir].1);
+            while col >= 0 && col < 8 && row >= 0 && row < 8 {
+                table[dir][index] |= 1 << (row * 8 + col);
+                col += DIRECTIONS[dir].0;
+                row += DIRECTIONS[dir].1;
+            }
+            index += 1;
+        }
+        dir += 1;
+    }
+    table
+}
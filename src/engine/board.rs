@@ -0,0 +1,406 @@
+use super::{GRID, Move, is_on_board, is_square_attacked, zobrist};
+use super::bitboard::{self, Bitboard};
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum FigureType {
+    Pawn,
+    Fort,
+    Bishop,
+    Knight,
+    King,
+    Queen
+}
+
+impl FigureType {
+    pub fn get_str(&self) -> &str {
+        match self {
+            &Self::Pawn => {"PAWN"},
+            &Self::Fort => {"FORT"},
+            &Self::Bishop => {"BISHOP"},
+            &Self::Knight => {"KNIGHT"},
+            &Self::King => {"KING"},
+            &Self::Queen => {"QUEEN"},
+        }
+    }
+}
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum ChessColor {
+    White,
+    Black,
+}
+
+impl ChessColor {
+    pub fn get_str(&self) -> &str {
+        match self {
+            &Self::White => {"WHITE"},
+            &Self::Black => {"BLACK"},
+        }
+    }
+
+    pub fn opposite(&self) -> ChessColor {
+        match self {
+            &Self::White => {ChessColor::Black},
+            &Self::Black => {ChessColor::White},
+        }
+    }
+}
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub struct Piece {
+    pub figure_type: FigureType,
+    pub color: ChessColor,
+}
+
+impl Piece {
+    pub fn build(figure_type: FigureType, color: ChessColor) -> Piece {
+        Piece {
+            figure_type,
+            color,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Default)]
+pub struct CastlingRights {
+    pub white_king_side: bool,
+    pub white_queen_side: bool,
+    pub black_king_side: bool,
+    pub black_queen_side: bool,
+}
+
+impl CastlingRights {
+    pub fn all() -> CastlingRights {
+        CastlingRights {
+            white_king_side: true,
+            white_queen_side: true,
+            black_king_side: true,
+            black_queen_side: true,
+        }
+    }
+
+    pub fn king_side(&self, color: ChessColor) -> bool {
+        match color {
+            ChessColor::White => self.white_king_side,
+            ChessColor::Black => self.black_king_side,
+        }
+    }
+
+    pub fn queen_side(&self, color: ChessColor) -> bool {
+        match color {
+            ChessColor::White => self.white_queen_side,
+            ChessColor::Black => self.black_queen_side,
+        }
+    }
+}
+
+// What make_move throws away, enough to take the move back with unmake_move.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub struct Undo {
+    pub moved: Piece,
+    pub captured: Option<Piece>,
+    pub castling: CastlingRights,
+    pub en_passant: Option<(i32, i32)>,
+    pub halfmove_clock: u32,
+}
+
+// The whole game state without any bevy in it. Squares are (col, row) like everywhere
+// else in the game, row 0 is white's back rank.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Position {
+    squares: [Option<Piece>; 64],
+    // the same figures again, one bitboard per colour and figure type
+    bitboards: [[Bitboard; 6]; 2],
+    pub side_to_move: ChessColor,
+    pub castling: CastlingRights,
+    pub en_passant: Option<(i32, i32)>,
+    pub halfmove_clock: u32,
+    pub fullmove_number: u32,
+    hash: u64,
+}
+
+impl Position {
+    pub fn empty() -> Position {
+        let mut position = Position {
+            squares: [None; 64],
+            bitboards: [[0; 6]; 2],
+            side_to_move: ChessColor::White,
+            castling: CastlingRights::default(),
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            hash: 0,
+        };
+        position.refresh_hash();
+        position
+    }
+
+    pub fn starting() -> Position {
+        let mut position = Position::empty();
+        let back_rank = [
+            FigureType::Fort,
+            FigureType::Knight,
+            FigureType::Bishop,
+            FigureType::Queen,
+            FigureType::King,
+            FigureType::Bishop,
+            FigureType::Knight,
+            FigureType::Fort,
+        ];
+
+        for col in 0..GRID {
+            position.set_piece((col, 0), Some(Piece::build(back_rank[col as usize], ChessColor::White)));
+            position.set_piece((col, 1), Some(Piece::build(FigureType::Pawn, ChessColor::White)));
+            position.set_piece((col, 6), Some(Piece::build(FigureType::Pawn, ChessColor::Black)));
+            position.set_piece((col, 7), Some(Piece::build(back_rank[col as usize], ChessColor::Black)));
+        }
+        position.castling = CastlingRights::all();
+        position.refresh_hash();
+
+        position
+    }
+
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    // make_move keeps the hash up to date on its own, this is for when the fields were set by hand
+    pub fn refresh_hash(&mut self) {
+        self.hash = zobrist::hash(self);
+    }
+
+    pub fn piece_at(&self, square: (i32, i32)) -> Option<Piece> {
+        if !is_on_board(square) {
+            return None;
+        }
+        self.squares[Position::index(square)]
+    }
+
+    pub fn set_piece(&mut self, square: (i32, i32), piece: Option<Piece>) {
+        let bit = bitboard::square_bit(square);
+        if let Some(old) = self.squares[Position::index(square)] {
+            self.hash ^= zobrist::piece_key(old, square);
+            self.bitboards[bitboard::color_index(old.color)][bitboard::figure_index(old.figure_type)] &= !bit;
+        }
+        if let Some(new) = piece {
+            self.hash ^= zobrist::piece_key(new, square);
+            self.bitboards[bitboard::color_index(new.color)][bitboard::figure_index(new.figure_type)] |= bit;
+        }
+        self.squares[Position::index(square)] = piece;
+    }
+
+    pub fn bitboard(&self, figure_type: FigureType, color: ChessColor) -> Bitboard {
+        self.bitboards[bitboard::color_index(color)][bitboard::figure_index(figure_type)]
+    }
+
+    pub fn color_bitboard(&self, color: ChessColor) -> Bitboard {
+        self.bitboards[bitboard::color_index(color)].iter().fold(0, |all, figures| all | figures)
+    }
+
+    pub fn occupied(&self) -> Bitboard {
+        self.color_bitboard(ChessColor::White) | self.color_bitboard(ChessColor::Black)
+    }
+
+    pub fn pieces(&self) -> impl Iterator<Item = ((i32, i32), Piece)> + '_ {
+        self.squares.iter()
+            .enumerate()
+            .filter_map(|(index, piece)| {
+                piece.map(|piece| (((index as i32) % GRID, (index as i32) / GRID), piece))
+            })
+    }
+
+    pub fn find_king(&self, color: ChessColor) -> Option<(i32, i32)> {
+        bitboard::squares(self.bitboard(FigureType::King, color)).next()
+    }
+
+    pub fn is_in_check(&self, color: ChessColor) -> bool {
+        match self.find_king(color) {
+            Some(king) => is_square_attacked(self, king, color.opposite()),
+            None => false,
+        }
+    }
+
+    // the en passant square only changes the position if a pawn is there to use it
+    pub fn can_take_en_passant(&self, square: (i32, i32)) -> bool {
+        let pawn_row = match self.side_to_move {
+            ChessColor::White => square.1 - 1,
+            ChessColor::Black => square.1 + 1,
+        };
+        let pawn = Some(Piece::build(FigureType::Pawn, self.side_to_move));
+        self.piece_at((square.0 - 1, pawn_row)) == pawn || self.piece_at((square.0 + 1, pawn_row)) == pawn
+    }
+
+    // K vs K, K+B vs K, K+N vs K and any number of bishops all on the same square colour
+    pub fn has_insufficient_material(&self) -> bool {
+        let others: Vec<((i32, i32), Piece)> = self.pieces()
+            .filter(|(_, piece)| piece.figure_type != FigureType::King)
+            .collect();
+        match others.as_slice() {
+            [] => true,
+            [(_, piece)] if piece.figure_type == FigureType::Knight => true,
+            _ => {
+                let square_color = (others[0].0.0 + others[0].0.1) % 2;
+                others.iter().all(|(square, piece)| {
+                    piece.figure_type == FigureType::Bishop && (square.0 + square.1) % 2 == square_color
+                })
+            }
+        }
+    }
+
+    // Plays the move and hands the turn over. No legality checks here, ask movegen for
+    // that first.
+    pub fn make_move(&mut self, chess_move: Move) -> Option<Piece> {
+        let (from, to) = (chess_move.from, chess_move.to);
+        let mut moving = self.piece_at(from).expect("No piece on the square to move from");
+        let is_promotion = self.is_promotion(from, to);
+        // figures are hashed by set_piece, the rest is swapped out here and back in at the end
+        self.hash ^= zobrist::state_key(self);
+        let captured = match chess_move.captured_square() {
+            Some(square) => {
+                let captured = self.piece_at(square);
+                self.set_piece(square, None);
+                captured
+            },
+            None => None,
+        };
+
+        if let Some((rook_from, rook_to)) = chess_move.castling_rook_move() {
+            let rook = self.piece_at(rook_from);
+            self.set_piece(rook_from, None);
+            self.set_piece(rook_to, rook);
+        }
+        if is_promotion {
+            moving.figure_type = chess_move.promotion.unwrap_or(FigureType::Queen);
+        }
+        self.set_piece(from, None);
+        self.set_piece(to, Some(moving));
+        self.update_castling_rights(from, to);
+
+        if moving.figure_type == FigureType::Pawn || is_promotion || captured.is_some() {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+        if self.side_to_move == ChessColor::Black {
+            self.fullmove_number += 1;
+        }
+        // only valid for the very next move
+        self.en_passant = if moving.figure_type == FigureType::Pawn && (to.1 - from.1).abs() == 2 {
+            Some((from.0, (from.1 + to.1) / 2))
+        } else {
+            None
+        };
+        self.side_to_move = self.side_to_move.opposite();
+        self.hash ^= zobrist::state_key(self);
+
+        captured
+    }
+
+    // has to be taken before the move is made
+    pub fn undo_state(&self, chess_move: Move) -> Undo {
+        Undo {
+            moved: self.piece_at(chess_move.from).expect("No piece on the square to move from"),
+            captured: chess_move.captured_square().and_then(|square| self.piece_at(square)),
+            castling: self.castling,
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+        }
+    }
+
+    // make_move backwards, `undo` is what undo_state gave right before the move
+    pub fn unmake_move(&mut self, chess_move: Move, undo: &Undo) {
+        self.hash ^= zobrist::state_key(self);
+        self.side_to_move = self.side_to_move.opposite();
+        if self.side_to_move == ChessColor::Black {
+            self.fullmove_number -= 1;
+        }
+        self.castling = undo.castling;
+        self.en_passant = undo.en_passant;
+        self.halfmove_clock = undo.halfmove_clock;
+
+        self.set_piece(chess_move.to, None);
+        self.set_piece(chess_move.from, Some(undo.moved));
+        if let Some((rook_from, rook_to)) = chess_move.castling_rook_move() {
+            let rook = self.piece_at(rook_to);
+            self.set_piece(rook_to, None);
+            self.set_piece(rook_from, rook);
+        }
+        if let Some(square) = chess_move.captured_square() {
+            self.set_piece(square, undo.captured);
+        }
+        self.hash ^= zobrist::state_key(self);
+    }
+
+    pub fn is_promotion(&self, from: (i32, i32), to: (i32, i32)) -> bool {
+        match self.piece_at(from) {
+            Some(piece) if piece.figure_type == FigureType::Pawn => to.1 == 0 || to.1 == GRID - 1,
+            _ => false,
+        }
+    }
+
+    // anything leaving or landing on a king or rook home square kills the matching right
+    fn update_castling_rights(&mut self, from: (i32, i32), to: (i32, i32)) {
+        for square in [from, to] {
+            match square {
+                (4, 0) => {
+                    self.castling.white_king_side = false;
+                    self.castling.white_queen_side = false;
+                },
+                (4, 7) => {
+                    self.castling.black_king_side = false;
+                    self.castling.black_queen_side = false;
+                },
+                (0, 0) => self.castling.white_queen_side = false,
+                (7, 0) => self.castling.white_king_side = false,
+                (0, 7) => self.castling.black_queen_side = false,
+                (7, 7) => self.castling.black_king_side = false,
+                _ => {},
+            }
+        }
+    }
+
+    fn index(square: (i32, i32)) -> usize {
+        (square.1 * GRID + square.0) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::fen;
+    use crate::engine::notation::from_uci;
+
+    fn is_dead(fen: &str) -> bool {
+        fen::parse(fen).unwrap().has_insufficient_material()
+    }
+
+    #[test]
+    fn insufficient_material() {
+        assert!(is_dead("4k3/8/8/8/8/8/8/4K3 w - - 0 1"));
+        assert!(is_dead("4k3/8/8/8/8/8/8/2B1K3 w - - 0 1"));
+        assert!(is_dead("4k3/8/8/8/8/8/8/1N2K3 w - - 0 1"));
+        // bishops on c1 and f8 both stand on dark squares
+        assert!(is_dead("4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1"));
+        assert!(!is_dead("2b1k3/8/8/8/8/8/8/2B1K3 w - - 0 1"));
+        // a mate with two knights can still be helped along
+        assert!(!is_dead("1n2k3/8/8/8/8/8/8/1N2K3 w - - 0 1"));
+        assert!(!is_dead("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"));
+    }
+
+    #[test]
+    fn knights_shuffle_into_a_threefold_repetition() {
+        let mut position = Position::starting();
+        let mut hashes = vec![position.hash()];
+        for uci in ["g1f3", "g8f6", "f3g1", "f6g8", "g1f3", "g8f6", "f3g1", "f6g8"] {
+            let chess_move = from_uci(&position, uci).unwrap();
+            position.make_move(chess_move);
+            hashes.push(position.hash());
+        }
+        let repetitions = hashes.iter().filter(|hash| **hash == position.hash()).count();
+        assert_eq!(repetitions, 3);
+        assert_eq!(position.hash(), Position::starting().hash());
+        // nothing was taken and no pawn moved, only the counters went on
+        assert_eq!(position.halfmove_clock, 8);
+        assert_eq!(fen::write(&position), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 8 5");
+    }
+}
@@ -0,0 +1,199 @@
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+use std::time::{Duration, Instant};
+
+use super::{Position, Move, fen};
+use super::notation::{from_san, to_san};
+use super::search::{self, SearchLimits};
+use crate::logger;
+
+// One position of a test suite. `bm` are the moves to find, `am` the ones to stay away from.
+pub struct EpdEntry {
+    pub position: Position,
+    pub id: Option<String>,
+    pub best_moves: Vec<Move>,
+    pub avoid_moves: Vec<Move>,
+}
+
+impl EpdEntry {
+    pub fn is_solved_by(&self, chess_move: Move) -> bool {
+        (self.best_moves.is_empty() || self.best_moves.contains(&chess_move))
+            && !self.avoid_moves.contains(&chess_move)
+    }
+}
+
+// The four FEN fields without the counters, then operations like bm Qd1+; id "WAC.001";
+// Opcodes other than bm, am and id are skipped.
+pub fn parse_line(line: &str) -> Result<EpdEntry, String> {
+    let mut rest = line.trim();
+    let mut fields = Vec::new();
+    for _ in 0..4 {
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        if end == 0 {
+            return Err(String::from("expected the four fields of a position"));
+        }
+        fields.push(&rest[..end]);
+        rest = rest[end..].trim_start();
+    }
+    let position = fen::parse(&fields.join(" ")).map_err(|error| error.to_string())?;
+
+    let mut entry = EpdEntry {
+        position,
+        id: None,
+        best_moves: Vec::new(),
+        avoid_moves: Vec::new(),
+    };
+    for operation in split_operations(rest) {
+        let (opcode, operands) = match operation.split_first() {
+            Some(split) => split,
+            None => continue,
+        };
+        match opcode.as_str() {
+            "id" => entry.id = operands.first().cloned(),
+            "bm" | "am" => {
+                let moves = operands.iter()
+                    .map(|san| from_san(&entry.position, san))
+                    .collect::<Result<Vec<Move>, String>>()?;
+                if opcode == "bm" {
+                    entry.best_moves = moves;
+                } else {
+                    entry.avoid_moves = moves;
+                }
+            },
+            _ => {},
+        }
+    }
+    Ok(entry)
+}
+
+// operations end with ';', a quoted operand may hold spaces and ';' itself
+fn split_operations(text: &str) -> Vec<Vec<String>> {
+    let mut operations = Vec::new();
+    let mut operation = Vec::new();
+    let mut word = String::new();
+    let mut quoted = false;
+    for symbol in text.chars() {
+        match symbol {
+            '"' => quoted = !quoted,
+            _ if quoted => word.push(symbol),
+            ';' => {
+                operation.extend(take_word(&mut word));
+                operations.push(std::mem::take(&mut operation));
+            },
+            _ if symbol.is_whitespace() => operation.extend(take_word(&mut word)),
+            _ => word.push(symbol),
+        }
+    }
+    operation.extend(take_word(&mut word));
+    if !operation.is_empty() {
+        operations.push(operation);
+    }
+    operations
+}
+
+fn take_word(word: &mut String) -> Option<String> {
+    if word.is_empty() { None } else { Some(std::mem::take(word)) }
+}
+
+// `sahister --epd <file>`, searches every position and reports which ones were solved
+pub fn run(path: &Path, limits: SearchLimits) {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(error) => {
+            logger::log(format!("Could not read {}: {}", path.display(), error));
+            return;
+        },
+    };
+    let stop = AtomicBool::new(false);
+    let start = Instant::now();
+    let (mut solved, mut total) = (0, 0);
+    let mut search_time = Duration::ZERO;
+
+    for (index, line) in text.lines().enumerate() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let entry = match parse_line(line) {
+            Ok(entry) => entry,
+            Err(error) => {
+                logger::log(format!("Line {} skipped: {}", index + 1, error));
+                continue;
+            },
+        };
+        let id = entry.id.clone().unwrap_or_else(|| format!("line {}", index + 1));
+        total += 1;
+
+        let position_start = Instant::now();
+        let info = match search::search(&entry.position, &[], limits, &stop, |_| {}) {
+            Some(info) => info,
+            None => {
+                logger::log(format!("{}: failed, no legal move", id));
+                continue;
+            },
+        };
+        let elapsed = position_start.elapsed();
+        search_time += elapsed;
+        let played = info.best_move().expect("A search result always has a move");
+        let is_solved = entry.is_solved_by(played);
+        if is_solved {
+            solved += 1;
+        }
+        let expected: Vec<String> = entry.best_moves.iter().map(|chess_move| to_san(&entry.position, *chess_move)).collect();
+        let avoided: Vec<String> = entry.avoid_moves.iter().map(|chess_move| to_san(&entry.position, *chess_move)).collect();
+        logger::log(format!(
+            "{}: {} {}{}{} depth {}, {} nodes in {:.2}s",
+            id,
+            if is_solved { "solved" } else { "failed" },
+            to_san(&entry.position, played),
+            if expected.is_empty() { String::new() } else { format!(", bm {}", expected.join(" ")) },
+            if avoided.is_empty() { String::new() } else { format!(", am {}", avoided.join(" ")) },
+            info.depth,
+            info.nodes,
+            elapsed.as_secs_f64(),
+        ));
+    }
+
+    logger::log(format!(
+        "\nSolved {} of {} in {:.2}s, {:.2}s per position",
+        solved,
+        total,
+        start.elapsed().as_secs_f64(),
+        if total > 0 { search_time.as_secs_f64() / total as f64 } else { 0.0 },
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::notation::to_uci;
+
+    #[test]
+    fn reads_operations() {
+        let entry = parse_line(r#"2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id "WAC 001; mate";"#).unwrap();
+        assert_eq!(entry.best_moves.iter().map(|chess_move| to_uci(*chess_move)).collect::<Vec<_>>(), ["g3g6"]);
+        assert_eq!(entry.id.as_deref(), Some("WAC 001; mate"));
+
+        let entry = parse_line("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - am f3 g4; bm e4 d4;").unwrap();
+        assert_eq!(entry.avoid_moves.len(), 2);
+        assert_eq!(entry.best_moves.len(), 2);
+        assert!(entry.is_solved_by(entry.best_moves[1]));
+        assert!(!entry.is_solved_by(entry.avoid_moves[0]));
+
+        assert!(parse_line("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - bm e5;").is_err());
+        assert!(parse_line("rnbqkbnr/pppppppp/8/8 w").is_err());
+    }
+
+    #[test]
+    fn solves_a_simple_suite() {
+        for line in [
+            r#"6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra8#; id "back rank";"#,
+            r#"4k3/8/8/3q4/8/8/3R4/4K3 w - - bm Rxd5; id "hanging queen";"#,
+        ] {
+            let entry = parse_line(line).unwrap();
+            let limits = SearchLimits { depth: Some(3), ..Default::default() };
+            let info = search::search(&entry.position, &[], limits, &AtomicBool::new(false), |_| {}).unwrap();
+            assert!(entry.is_solved_by(info.best_move().unwrap()), "{:?}", entry.id);
+        }
+    }
+}
@@ -0,0 +1,283 @@
+use std::fmt;
+
+use super::{Position, Piece, CastlingRights, FigureType, ChessColor, GRID, square_name};
+use super::notation::figure_letter;
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum FenField {
+    Placement,
+    SideToMove,
+    Castling,
+    EnPassant,
+    HalfmoveClock,
+    FullmoveNumber,
+}
+
+impl FenField {
+    pub fn get_str(&self) -> &str {
+        match self {
+            Self::Placement => {"piece placement"},
+            Self::SideToMove => {"side to move"},
+            Self::Castling => {"castling"},
+            Self::EnPassant => {"en passant"},
+            Self::HalfmoveClock => {"halfmove clock"},
+            Self::FullmoveNumber => {"fullmove number"},
+        }
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct FenError {
+    pub field: FenField,
+    pub value: String,
+    pub message: String,
+}
+
+impl FenError {
+    fn build(field: FenField, value: &str, message: impl Into<String>) -> FenError {
+        FenError {
+            field,
+            value: value.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid FEN, {} field \"{}\": {}", self.field.get_str(), self.value, self.message)
+    }
+}
+
+// The two counters may be left out like in EPD, they then default to 0 and 1.
+pub fn parse(fen: &str) -> Result<Position, FenError> {
+    let fields: Vec<&str> = fen.split_whitespace().collect();
+    if fields.len() < 4 || fields.len() > 6 {
+        return Err(FenError::build(FenField::Placement, fen, format!("expected 4 to 6 fields, found {}", fields.len())));
+    }
+
+    let mut position = Position::empty();
+    parse_placement(&mut position, fields[0])?;
+
+    position.side_to_move = match fields[1] {
+        "w" => ChessColor::White,
+        "b" => ChessColor::Black,
+        other => return Err(FenError::build(FenField::SideToMove, other, "expected w or b")),
+    };
+    position.castling = parse_castling(&position, fields[2])?;
+    position.en_passant = parse_en_passant(&position, fields[3])?;
+    if let Some(halfmove_clock) = fields.get(4) {
+        position.halfmove_clock = halfmove_clock.parse()
+            .map_err(|_| FenError::build(FenField::HalfmoveClock, halfmove_clock, "expected a number of plies"))?;
+    }
+    if let Some(fullmove_number) = fields.get(5) {
+        position.fullmove_number = match fullmove_number.parse() {
+            Ok(number) if number > 0 => number,
+            _ => return Err(FenError::build(FenField::FullmoveNumber, fullmove_number, "expected a move number from 1 up")),
+        };
+    }
+
+    if position.is_in_check(position.side_to_move.opposite()) {
+        return Err(FenError::build(FenField::SideToMove, fields[1], "the side that just moved is still in check"));
+    }
+    position.refresh_hash();
+    Ok(position)
+}
+
+pub fn write(position: &Position) -> String {
+    let mut ranks = Vec::new();
+    for row in (0..GRID).rev() {
+        let mut rank = String::new();
+        let mut empty = 0;
+        for col in 0..GRID {
+            match position.piece_at((col, row)) {
+                Some(piece) => {
+                    if empty > 0 {
+                        rank.push_str(&empty.to_string());
+                        empty = 0;
+                    }
+                    rank.push(piece_symbol(piece));
+                },
+                None => empty += 1,
+            }
+        }
+        if empty > 0 {
+            rank.push_str(&empty.to_string());
+        }
+        ranks.push(rank);
+    }
+
+    let side_to_move = match position.side_to_move {
+        ChessColor::White => "w",
+        ChessColor::Black => "b",
+    };
+    let mut castling = String::new();
+    for (right, symbol) in [
+        (position.castling.white_king_side, 'K'),
+        (position.castling.white_queen_side, 'Q'),
+        (position.castling.black_king_side, 'k'),
+        (position.castling.black_queen_side, 'q'),
+    ] {
+        if right {
+            castling.push(symbol);
+        }
+    }
+    if castling.is_empty() {
+        castling.push('-');
+    }
+    let en_passant = position.en_passant.map_or(String::from("-"), square_name);
+
+    format!(
+        "{} {} {} {} {} {}",
+        ranks.join("/"), side_to_move, castling, en_passant, position.halfmove_clock, position.fullmove_number,
+    )
+}
+
+fn piece_symbol(piece: Piece) -> char {
+    let letter = figure_letter(piece.figure_type);
+    match piece.color {
+        ChessColor::White => letter,
+        ChessColor::Black => letter.to_ascii_lowercase(),
+    }
+}
+
+fn parse_placement(position: &mut Position, placement: &str) -> Result<(), FenError> {
+    let error = |message: String| FenError::build(FenField::Placement, placement, message);
+    let ranks: Vec<&str> = placement.split('/').collect();
+    if ranks.len() != GRID as usize {
+        return Err(error(format!("expected 8 ranks, found {}", ranks.len())));
+    }
+
+    for (index, rank) in ranks.iter().enumerate() {
+        let row = GRID - 1 - index as i32;
+        let mut col = 0;
+        for symbol in rank.chars() {
+            if let Some(skip) = symbol.to_digit(10).filter(|skip| (1..=8).contains(skip)) {
+                col += skip as i32;
+                continue;
+            }
+            let figure_type = match symbol.to_ascii_lowercase() {
+                'p' => FigureType::Pawn,
+                'n' => FigureType::Knight,
+                'b' => FigureType::Bishop,
+                'r' => FigureType::Fort,
+                'q' => FigureType::Queen,
+                'k' => FigureType::King,
+                _ => return Err(error(format!("unknown piece '{}' on rank {}", symbol, row + 1))),
+            };
+            if col >= GRID {
+                return Err(error(format!("rank {} has more than 8 squares", row + 1)));
+            }
+            if figure_type == FigureType::Pawn && (row == 0 || row == GRID - 1) {
+                return Err(error(format!("pawn on rank {}", row + 1)));
+            }
+            let color = if symbol.is_ascii_uppercase() { ChessColor::White } else { ChessColor::Black };
+            position.set_piece((col, row), Some(Piece::build(figure_type, color)));
+            col += 1;
+        }
+        if col != GRID {
+            return Err(error(format!("rank {} does not have 8 squares", row + 1)));
+        }
+    }
+
+    for color in [ChessColor::White, ChessColor::Black] {
+        let kings = position.pieces()
+            .filter(|(_, piece)| *piece == Piece::build(FigureType::King, color))
+            .count();
+        if kings != 1 {
+            return Err(error(format!("{} has {} kings", color.get_str().to_lowercase(), kings)));
+        }
+    }
+    Ok(())
+}
+
+// rights whose king or rook is not on its home square are refused
+fn parse_castling(position: &Position, castling: &str) -> Result<CastlingRights, FenError> {
+    let mut rights = CastlingRights::default();
+    if castling == "-" {
+        return Ok(rights);
+    }
+    for symbol in castling.chars() {
+        let (color, rook_col, right) = match symbol {
+            'K' => (ChessColor::White, GRID - 1, &mut rights.white_king_side),
+            'Q' => (ChessColor::White, 0, &mut rights.white_queen_side),
+            'k' => (ChessColor::Black, GRID - 1, &mut rights.black_king_side),
+            'q' => (ChessColor::Black, 0, &mut rights.black_queen_side),
+            _ => return Err(FenError::build(FenField::Castling, castling, format!("unknown castling right '{}'", symbol))),
+        };
+        if *right {
+            return Err(FenError::build(FenField::Castling, castling, format!("'{}' is there twice", symbol)));
+        }
+        let back_row = match color {
+            ChessColor::White => 0,
+            ChessColor::Black => GRID - 1,
+        };
+        if position.piece_at((4, back_row)) != Some(Piece::build(FigureType::King, color))
+            || position.piece_at((rook_col, back_row)) != Some(Piece::build(FigureType::Fort, color)) {
+            return Err(FenError::build(FenField::Castling, castling, format!("'{}' without king and rook on their squares", symbol)));
+        }
+        *right = true;
+    }
+    Ok(rights)
+}
+
+// the square must be right behind a pawn of the side that just moved
+fn parse_en_passant(position: &Position, en_passant: &str) -> Result<Option<(i32, i32)>, FenError> {
+    if en_passant == "-" {
+        return Ok(None);
+    }
+    let error = |message: &str| FenError::build(FenField::EnPassant, en_passant, message);
+    let symbols: Vec<char> = en_passant.chars().collect();
+    let square = match symbols.as_slice() {
+        [file @ 'a'..='h', rank @ '1'..='8'] => ((*file as u8 - b'a') as i32, (*rank as u8 - b'1') as i32),
+        _ => return Err(error("expected - or a square like e3")),
+    };
+    let (row, pawn_row) = match position.side_to_move {
+        ChessColor::White => (5, 4),
+        ChessColor::Black => (2, 3),
+    };
+    if square.1 != row {
+        return Err(error("not on the rank a pawn of the side that just moved skips"));
+    }
+    let pawn = Some(Piece::build(FigureType::Pawn, position.side_to_move.opposite()));
+    if position.piece_at((square.0, pawn_row)) != pawn {
+        return Err(error("no pawn in front of it that could have skipped it"));
+    }
+    Ok(Some(square))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    #[test]
+    fn starting_fen_round_trip() {
+        assert_eq!(parse(STARTING_FEN), Ok(Position::starting()));
+        assert_eq!(write(&Position::starting()), STARTING_FEN);
+    }
+
+    #[test]
+    fn fen_round_trip() {
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 b - - 12 40",
+        ] {
+            assert_eq!(write(&parse(fen).unwrap()), fen);
+        }
+    }
+
+    #[test]
+    fn errors_point_at_the_field() {
+        let field = |fen: &str| parse(fen).unwrap_err().field;
+        assert_eq!(field("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq - 0 1"), FenField::Placement);
+        assert_eq!(field("rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), FenField::Placement);
+        assert_eq!(field("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1"), FenField::SideToMove);
+        assert_eq!(field("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkx - 0 1"), FenField::Castling);
+        assert_eq!(field("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e4 0 1"), FenField::EnPassant);
+        assert_eq!(field("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - x 1"), FenField::HalfmoveClock);
+        assert_eq!(field("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 0"), FenField::FullmoveNumber);
+    }
+}
@@ -0,0 +1,199 @@
+use super::{Position, Piece, Move, FigureType, ChessColor, is_on_board};
+use super::bitboard::{self, Bitboard};
+pub use super::bitboard::is_square_attacked;
+
+#[cfg(test)]
+pub mod classic;
+
+const PROMOTIONS: [FigureType; 4] = [FigureType::Queen, FigureType::Fort, FigureType::Bishop, FigureType::Knight];
+
+// only the moves after which the mover's own king is not attacked, a pawn reaching the last
+// row gets one move per figure it can turn into
+pub fn get_legalmoves(
+    position: &Position,
+    from: (i32, i32),
+) -> Vec<Move> {
+    match position.piece_at(from) {
+        Some(piece) => moves_from(position, from, &KingSafety::of(position, piece.color)),
+        None => Vec::new(),
+    }
+}
+
+pub fn has_legal_move(position: &Position) -> bool {
+    let safety = KingSafety::of(position, position.side_to_move);
+    bitboard::squares(position.color_bitboard(position.side_to_move))
+        .any(|from| legal_targets(position, from, &safety) != 0)
+}
+
+// every legal move of the side to move
+pub fn legal_moves(position: &Position) -> Vec<Move> {
+    let safety = KingSafety::of(position, position.side_to_move);
+    bitboard::squares(position.color_bitboard(position.side_to_move))
+        .flat_map(|from| moves_from(position, from, &safety))
+        .collect()
+}
+
+fn moves_from(position: &Position, from: (i32, i32), safety: &KingSafety) -> Vec<Move> {
+    let mut moves = Vec::new();
+    for to in bitboard::squares(legal_targets(position, from, safety)) {
+        if position.is_promotion(from, to) {
+            for promotion in PROMOTIONS {
+                moves.push(Move::build(position, from, to, Some(promotion)));
+            }
+        } else {
+            moves.push(Move::build(position, from, to, None));
+        }
+    }
+    moves
+}
+
+// What keeps the king of one side out of check, worked out once per position: while in check
+// only moves onto `check_mask` help, and a pinned figure has to stay on its line.
+struct KingSafety {
+    king: Option<(i32, i32)>,
+    checkers: Bitboard,
+    check_mask: Bitboard,
+    pins: Vec<(Bitboard, Bitboard)>,
+}
+
+impl KingSafety {
+    fn of(position: &Position, color: ChessColor) -> KingSafety {
+        let king = match position.find_king(color) {
+            Some(king) => king,
+            None => return KingSafety { king: None, checkers: 0, check_mask: !0, pins: Vec::new() },
+        };
+        let occupied = position.occupied();
+        let checkers = bitboard::attackers(position, king, color.opposite(), occupied);
+        let check_mask = match checkers.count_ones() {
+            0 => !0,
+            // taking the checker or stepping in between, the line is empty for knights and pawns
+            1 => checkers | bitboard::line_to(king, checkers, occupied),
+            _ => 0,
+        };
+        KingSafety {
+            king: Some(king),
+            checkers,
+            check_mask,
+            pins: bitboard::pins(position, king, color),
+        }
+    }
+}
+
+fn legal_targets(position: &Position, from: (i32, i32), safety: &KingSafety) -> Bitboard {
+    let piece = match position.piece_at(from) {
+        Some(piece) => piece,
+        None => return 0,
+    };
+    let targets = pseudo_legal_targets(position, from);
+    let king = match safety.king {
+        Some(king) => king,
+        None => return targets,
+    };
+
+    if piece.figure_type == FigureType::King {
+        // the king itself must not block a slider looking at the square it steps to
+        let occupied = position.occupied() & !bitboard::square_bit(king);
+        return bitboard::squares(targets)
+            .filter(|to| bitboard::attackers(position, *to, piece.color.opposite(), occupied) == 0)
+            .fold(0, |legal, to| legal | bitboard::square_bit(to));
+    }
+    if safety.checkers.count_ones() > 1 {
+        return 0;
+    }
+
+    // en passant takes two figures off their squares at once, it is simply tried out
+    let en_passant = match position.en_passant {
+        Some(square) if piece.figure_type == FigureType::Pawn => targets & bitboard::square_bit(square),
+        _ => 0,
+    };
+    let pin_line = safety.pins.iter()
+        .find(|(pinned, _)| pinned & bitboard::square_bit(from) != 0)
+        .map_or(!0, |(_, line)| *line);
+    let mut legal = targets & !en_passant & safety.check_mask & pin_line;
+    for to in bitboard::squares(en_passant) {
+        let mut after_move = position.clone();
+        after_move.make_move(Move::build(position, from, to, None));
+        if !after_move.is_in_check(piece.color) {
+            legal |= bitboard::square_bit(to);
+        }
+    }
+    legal
+}
+
+fn pseudo_legal_targets(
+    position: &Position,
+    from: (i32, i32),
+) -> Bitboard {
+    let piece = match position.piece_at(from) {
+        Some(piece) => piece,
+        None => return 0,
+    };
+    let own = position.color_bitboard(piece.color);
+    let enemy = position.color_bitboard(piece.color.opposite());
+    let occupied = own | enemy;
+
+    let targets = match piece.figure_type {
+        FigureType::Pawn => {
+            let (dir, start_row) = match piece.color {
+                ChessColor::Black => (-1, 6),
+                ChessColor::White => (1, 1),
+            };
+            let mut targets = 0;
+            // pushes only onto empty squares, a pawn still on its start row hasn't moved yet
+            let one_step = (from.0, from.1 + dir);
+            if is_on_board(one_step) && occupied & bitboard::square_bit(one_step) == 0 {
+                targets |= bitboard::square_bit(one_step);
+                let two_step = (from.0, from.1 + 2 * dir);
+                if from.1 == start_row && occupied & bitboard::square_bit(two_step) == 0 {
+                    targets |= bitboard::square_bit(two_step);
+                }
+            }
+            // captures only diagonally, en passant only onto the square the enemy pawn just skipped
+            let mut capturable = enemy;
+            if let Some(en_passant) = position.en_passant {
+                if en_passant.1 == start_row + 4 * dir {
+                    capturable |= bitboard::square_bit(en_passant);
+                }
+            }
+            targets | bitboard::pawn_attacks(from, piece.color) & capturable
+        },
+        FigureType::Fort => bitboard::rook_attacks(from, occupied),
+        FigureType::Bishop => bitboard::bishop_attacks(from, occupied),
+        FigureType::Queen => bitboard::rook_attacks(from, occupied) | bitboard::bishop_attacks(from, occupied),
+        FigureType::Knight => bitboard::knight_attacks(from),
+        FigureType::King => bitboard::king_attacks(from) | castling_targets(position, from, piece.color, occupied),
+    };
+    targets & !own
+}
+
+// king two squares towards an unmoved rook, nothing in between and never through or into check
+fn castling_targets(
+    position: &Position,
+    from: (i32, i32),
+    color: ChessColor,
+    occupied: Bitboard,
+) -> Bitboard {
+    let back_row = match color {
+        ChessColor::White => 0,
+        ChessColor::Black => 7,
+    };
+    if from != (4, back_row) || is_square_attacked(position, from, color.opposite()) {
+        return 0;
+    }
+    let rook = Some(Piece::build(FigureType::Fort, color));
+    let is_free = |squares: &[(i32, i32)]| squares.iter().all(|square| occupied & bitboard::square_bit(*square) == 0);
+    let is_safe = |squares: &[(i32, i32)]| squares.iter().all(|square| !is_square_attacked(position, *square, color.opposite()));
+
+    let mut targets = 0;
+    if position.castling.king_side(color) && position.piece_at((7, back_row)) == rook
+        && is_free(&[(5, back_row), (6, back_row)])
+        && is_safe(&[(5, back_row), (6, back_row)]) {
+        targets |= bitboard::square_bit((6, back_row));
+    }
+    if position.castling.queen_side(color) && position.piece_at((0, back_row)) == rook
+        && is_free(&[(1, back_row), (2, back_row), (3, back_row)])
+        && is_safe(&[(2, back_row), (3, back_row)]) {
+        targets |= bitboard::square_bit((2, back_row));
+    }
+    targets
+}
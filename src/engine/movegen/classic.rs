@@ -0,0 +1,203 @@
+// The first move generator, one HashSet per figure and ray walking on (col, row) pairs.
+// Only kept around for the tests, as a second opinion and as the baseline of the benchmark.
+use std::collections::HashSet;
+
+use crate::engine::{Position, Piece, Move, FigureType, ChessColor, is_on_board};
+
+const STRAIGHT_DIRECTIONS: [(i32, i32); 4] = [(0, 1), (0, -1), (1, 0), (-1, 0)];
+const DIAGONAL_DIRECTIONS: [(i32, i32); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+const KNIGHT_OFFSETS: [(i32, i32); 8] = [(2, 1), (2, -1), (-2, 1), (-2, -1), (1, 2), (-1, 2), (1, -2), (-1, -2)];
+const KING_OFFSETS: [(i32, i32); 8] = [(1, 1), (1, 0), (1, -1), (0, 1), (0, -1), (-1, 1), (-1, 0), (-1, -1)];
+
+// only the moves after which the mover's own king is not attacked
+pub fn get_legalmoves(
+    position: &Position,
+    from: (i32, i32),
+) -> HashSet<(i32, i32)> {
+    let mut set_moves = pseudo_legal_moves(position, from);
+    if let Some(piece) = position.piece_at(from) {
+        set_moves.retain(|to| {
+            let mut after_move = position.clone();
+            after_move.make_move(Move::build(position, from, *to, None));
+            !is_in_check(&after_move, piece.color)
+        });
+    }
+    set_moves
+}
+
+fn is_in_check(position: &Position, color: ChessColor) -> bool {
+    match position.find_king(color) {
+        Some(king) => is_square_attacked(position, king, color.opposite()),
+        None => false,
+    }
+}
+
+pub fn pseudo_legal_moves(
+    position: &Position,
+    from: (i32, i32),
+) -> HashSet<(i32, i32)> {
+    let mut set_moves = HashSet::new();
+    let piece = match position.piece_at(from) {
+        Some(piece) => piece,
+        None => return set_moves,
+    };
+
+    let start_pos = from;
+    match piece.figure_type {
+        FigureType::Pawn => {
+            let (dir, start_row): (i32, i32) = match piece.color {
+                ChessColor::Black => {
+                    (-1, 6)
+                },
+                ChessColor::White => {
+                    (1, 1)
+                }
+            };
+            // pushes only onto empty squares, a pawn still on its start row hasn't moved yet
+            let one_step = (start_pos.0, start_pos.1 + dir);
+            if is_on_board(one_step) && position.piece_at(one_step).is_none() {
+                set_moves.insert(one_step);
+                let two_step = (start_pos.0, start_pos.1 + 2 * dir);
+                if start_pos.1 == start_row && position.piece_at(two_step).is_none() {
+                    set_moves.insert(two_step);
+                }
+            }
+            // captures only diagonally, en passant only onto the square the enemy pawn just skipped
+            let en_passant_row = start_row + 4 * dir;
+            for side in [-1, 1] {
+                let target = (start_pos.0 + side, start_pos.1 + dir);
+                if let Some(other) = position.piece_at(target) {
+                    if other.color != piece.color {
+                        set_moves.insert(target);
+                    }
+                } else if position.en_passant == Some(target) && target.1 == en_passant_row {
+                    set_moves.insert(target);
+                }
+            }
+        },
+        FigureType::Fort => {
+            sliding_moves(position, start_pos, piece.color, &STRAIGHT_DIRECTIONS, &mut set_moves);
+        },
+        FigureType::Bishop => {
+            sliding_moves(position, start_pos, piece.color, &DIAGONAL_DIRECTIONS, &mut set_moves);
+        },
+        FigureType::Knight => {
+            for offset in KNIGHT_OFFSETS {
+                set_moves.insert((start_pos.0 + offset.0, start_pos.1 + offset.1));
+            }
+        },
+        FigureType::King => {
+            for offset in KING_OFFSETS {
+                set_moves.insert((start_pos.0 + offset.0, start_pos.1 + offset.1));
+            }
+            castling_moves(position, start_pos, piece.color, &mut set_moves);
+        },
+        FigureType::Queen => {
+            sliding_moves(position, start_pos, piece.color, &STRAIGHT_DIRECTIONS, &mut set_moves);
+            sliding_moves(position, start_pos, piece.color, &DIAGONAL_DIRECTIONS, &mut set_moves);
+        }
+    }
+    set_moves.retain(|square| {
+        is_on_board(*square) && position.piece_at(*square).is_none_or(|other| other.color != piece.color)
+    });
+    set_moves
+}
+
+// walks every direction until the edge or the first figure, which is only taken if it is an enemy
+fn sliding_moves(
+    position: &Position,
+    from: (i32, i32),
+    color: ChessColor,
+    directions: &[(i32, i32)],
+    set_moves: &mut HashSet<(i32, i32)>,
+) {
+    for dir in directions {
+        let mut square = (from.0 + dir.0, from.1 + dir.1);
+        while is_on_board(square) {
+            match position.piece_at(square) {
+                Some(blocker) => {
+                    if blocker.color != color {
+                        set_moves.insert(square);
+                    }
+                    break;
+                },
+                None => {
+                    set_moves.insert(square);
+                }
+            }
+            square = (square.0 + dir.0, square.1 + dir.1);
+        }
+    }
+}
+
+// king two squares towards an unmoved rook, nothing in between and never through or into check
+fn castling_moves(
+    position: &Position,
+    from: (i32, i32),
+    color: ChessColor,
+    set_moves: &mut HashSet<(i32, i32)>,
+) {
+    let back_row = match color {
+        ChessColor::White => 0,
+        ChessColor::Black => 7,
+    };
+    if from != (4, back_row) || is_in_check(position, color) {
+        return;
+    }
+    let rook = Some(Piece::build(FigureType::Fort, color));
+
+    if position.castling.king_side(color) && position.piece_at((7, back_row)) == rook {
+        let empty = [(5, back_row), (6, back_row)];
+        if empty.iter().all(|square| position.piece_at(*square).is_none())
+            && empty.iter().all(|square| !is_square_attacked(position, *square, color.opposite())) {
+            set_moves.insert((6, back_row));
+        }
+    }
+    if position.castling.queen_side(color) && position.piece_at((0, back_row)) == rook {
+        let empty = [(1, back_row), (2, back_row), (3, back_row)];
+        let walked = [(2, back_row), (3, back_row)];
+        if empty.iter().all(|square| position.piece_at(*square).is_none())
+            && walked.iter().all(|square| !is_square_attacked(position, *square, color.opposite())) {
+            set_moves.insert((2, back_row));
+        }
+    }
+}
+
+pub fn is_square_attacked(
+    position: &Position,
+    square: (i32, i32),
+    by: ChessColor,
+) -> bool {
+    let is_attacker = |target: (i32, i32), figure_types: &[FigureType]| {
+        match position.piece_at(target) {
+            Some(piece) => piece.color == by && figure_types.contains(&piece.figure_type),
+            None => false,
+        }
+    };
+
+    // pawns attack towards the side they are walking to, so look backwards from the square
+    let pawn_dir = match by {
+        ChessColor::White => -1,
+        ChessColor::Black => 1,
+    };
+    if is_attacker((square.0 - 1, square.1 + pawn_dir), &[FigureType::Pawn])
+        || is_attacker((square.0 + 1, square.1 + pawn_dir), &[FigureType::Pawn]) {
+        return true;
+    }
+    if KNIGHT_OFFSETS.iter().any(|offset| is_attacker((square.0 + offset.0, square.1 + offset.1), &[FigureType::Knight])) {
+        return true;
+    }
+    if KING_OFFSETS.iter().any(|offset| is_attacker((square.0 + offset.0, square.1 + offset.1), &[FigureType::King])) {
+        return true;
+    }
+
+    let first_blocker = |dir: &(i32, i32)| {
+        let mut target = (square.0 + dir.0, square.1 + dir.1);
+        while is_on_board(target) && position.piece_at(target).is_none() {
+            target = (target.0 + dir.0, target.1 + dir.1);
+        }
+        target
+    };
+    STRAIGHT_DIRECTIONS.iter().any(|dir| is_attacker(first_blocker(dir), &[FigureType::Fort, FigureType::Queen]))
+        || DIAGONAL_DIRECTIONS.iter().any(|dir| is_attacker(first_blocker(dir), &[FigureType::Bishop, FigureType::Queen]))
+}
@@ -0,0 +1,71 @@
+use std::fmt;
+
+use super::{Position, FigureType, GRID, square_name};
+
+// A single move with the special cases already worked out from the position it is played in,
+// so whoever applies it does not have to look at the board again to know what happens.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub struct Move {
+    pub from: (i32, i32),
+    pub to: (i32, i32),
+    pub promotion: Option<FigureType>,
+    pub is_capture: bool,
+    pub is_castling: bool,
+    pub is_en_passant: bool,
+}
+
+impl Move {
+    pub fn build(position: &Position, from: (i32, i32), to: (i32, i32), promotion: Option<FigureType>) -> Move {
+        let (is_pawn, is_king) = match position.piece_at(from) {
+            Some(piece) => (piece.figure_type == FigureType::Pawn, piece.figure_type == FigureType::King),
+            None => (false, false),
+        };
+        // a pawn going sideways onto an empty square can only be taking en passant
+        let is_en_passant = is_pawn && from.0 != to.0 && position.piece_at(to).is_none();
+        Move {
+            from,
+            to,
+            promotion,
+            is_capture: position.piece_at(to).is_some() || is_en_passant,
+            is_castling: is_king && (to.0 - from.0).abs() == 2,
+            is_en_passant,
+        }
+    }
+
+    // Where the figure taken by this move stands, en passant takes a pawn that is not on `to`.
+    pub fn captured_square(&self) -> Option<(i32, i32)> {
+        if self.is_en_passant {
+            Some((self.to.0, self.from.1))
+        } else if self.is_capture {
+            Some(self.to)
+        } else {
+            None
+        }
+    }
+
+    // Castling is played as a king move, the rook then jumps over the king.
+    pub fn castling_rook_move(&self) -> Option<((i32, i32), (i32, i32))> {
+        if !self.is_castling {
+            return None;
+        }
+        if self.to.0 > self.from.0 {
+            Some(((GRID - 1, self.from.1), (self.to.0 - 1, self.from.1)))
+        } else {
+            Some(((0, self.from.1), (self.to.0 + 1, self.from.1)))
+        }
+    }
+}
+
+// e2e4, e7e8q
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let promotion = match self.promotion {
+            Some(FigureType::Queen) => "q",
+            Some(FigureType::Fort) => "r",
+            Some(FigureType::Bishop) => "b",
+            Some(FigureType::Knight) => "n",
+            _ => "",
+        };
+        write!(f, "{}{}{}", square_name(self.from), square_name(self.to), promotion)
+    }
+}
@@ -0,0 +1,238 @@
+use super::{Position, Move, FigureType, legal_moves, has_legal_move, square_name};
+
+// Standard algebraic notation of a move about to be played in `position`: Nf3, exd5, O-O, e8=Q, Qh4#
+pub fn to_san(position: &Position, chess_move: Move) -> String {
+    let piece = position.piece_at(chess_move.from).expect("No piece on the square to move from");
+    let mut san = String::new();
+
+    if chess_move.is_castling {
+        san.push_str(if chess_move.to.0 > chess_move.from.0 { "O-O" } else { "O-O-O" });
+    } else {
+        if piece.figure_type == FigureType::Pawn {
+            if chess_move.is_capture {
+                san.push(file_letter(chess_move.from.0));
+            }
+        } else {
+            san.push(figure_letter(piece.figure_type));
+            san.push_str(&disambiguation(position, chess_move, piece.figure_type));
+        }
+        if chess_move.is_capture {
+            san.push('x');
+        }
+        san.push_str(&square_name(chess_move.to));
+        if let Some(promotion) = chess_move.promotion {
+            san.push('=');
+            san.push(figure_letter(promotion));
+        }
+    }
+
+    let mut after_move = position.clone();
+    after_move.make_move(chess_move);
+    if after_move.is_in_check(after_move.side_to_move) {
+        san.push(if has_legal_move(&after_move) { '+' } else { '#' });
+    }
+    san
+}
+
+pub fn figure_letter(figure_type: FigureType) -> char {
+    match figure_type {
+        FigureType::Pawn => 'P',
+        FigureType::Knight => 'N',
+        FigureType::Bishop => 'B',
+        FigureType::Fort => 'R',
+        FigureType::Queen => 'Q',
+        FigureType::King => 'K',
+    }
+}
+
+fn file_letter(col: i32) -> char {
+    (b'a' + col as u8) as char
+}
+
+// the file if that tells the figures apart, else the rank, else both
+fn disambiguation(position: &Position, chess_move: Move, figure_type: FigureType) -> String {
+    let others: Vec<(i32, i32)> = legal_moves(position)
+        .into_iter()
+        .filter(|other| other.to == chess_move.to && other.from != chess_move.from)
+        .filter(|other| position.piece_at(other.from).map(|piece| piece.figure_type) == Some(figure_type))
+        .map(|other| other.from)
+        .collect();
+    if others.is_empty() {
+        String::new()
+    } else if others.iter().all(|from| from.0 != chess_move.from.0) {
+        file_letter(chess_move.from.0).to_string()
+    } else if others.iter().all(|from| from.1 != chess_move.from.1) {
+        (chess_move.from.1 + 1).to_string()
+    } else {
+        square_name(chess_move.from)
+    }
+}
+
+// Reads a SAN move like Nbd2, exd6, O-O or e8=Q and finds the one legal move it stands for.
+// Check marks and annotations at the end are ignored.
+pub fn from_san(position: &Position, san: &str) -> Result<Move, String> {
+    let text = san.trim_end_matches(['+', '#', '!', '?']);
+    let legal = legal_moves(position);
+    let figure_at = |square: (i32, i32)| position.piece_at(square).map(|piece| piece.figure_type);
+
+    let castling = match text {
+        "O-O" | "0-0" => Some(true),
+        "O-O-O" | "0-0-0" => Some(false),
+        _ => None,
+    };
+    if let Some(king_side) = castling {
+        return legal.into_iter()
+            .find(|chess_move| chess_move.is_castling && (chess_move.to.0 > chess_move.from.0) == king_side)
+            .ok_or_else(|| format!("{} is not possible here", san));
+    }
+
+    let mut symbols: Vec<char> = text.chars().filter(|symbol| *symbol != 'x' && *symbol != '-').collect();
+    let figure_type = match symbols.first().and_then(|symbol| figure_from_letter(*symbol)) {
+        Some(figure_type) if figure_type != FigureType::Pawn => {
+            symbols.remove(0);
+            figure_type
+        },
+        _ => FigureType::Pawn,
+    };
+    // e8=Q and e8Q both show up in the wild
+    let promotion = match symbols.last().and_then(|symbol| figure_from_letter(*symbol)) {
+        Some(promotion) if figure_type == FigureType::Pawn => {
+            symbols.pop();
+            if symbols.last() == Some(&'=') {
+                symbols.pop();
+            }
+            Some(promotion)
+        },
+        _ => None,
+    };
+    if symbols.len() < 2 {
+        return Err(format!("{} is not a move", san));
+    }
+    let to = match parse_square(&symbols[symbols.len() - 2..]) {
+        Some(to) => to,
+        None => return Err(format!("{} has no target square", san)),
+    };
+    let mut from_file = None;
+    let mut from_rank = None;
+    for symbol in &symbols[..symbols.len() - 2] {
+        match symbol {
+            'a'..='h' => from_file = Some((*symbol as u8 - b'a') as i32),
+            '1'..='8' => from_rank = Some((*symbol as u8 - b'1') as i32),
+            _ => return Err(format!("{} has an unexpected '{}'", san, symbol)),
+        }
+    }
+
+    let candidates: Vec<Move> = legal.into_iter()
+        .filter(|chess_move| chess_move.to == to && figure_at(chess_move.from) == Some(figure_type))
+        .filter(|chess_move| from_file.is_none_or(|col| chess_move.from.0 == col))
+        .filter(|chess_move| from_rank.is_none_or(|row| chess_move.from.1 == row))
+        .filter(|chess_move| chess_move.promotion == promotion)
+        .collect();
+    match candidates.as_slice() {
+        [chess_move] => Ok(*chess_move),
+        [] => Err(format!("{} is not a legal move", san)),
+        _ => Err(format!("{} could be more than one move", san)),
+    }
+}
+
+// Long algebraic notation as UCI engines use it: e2e4, e1g1 for castling, e7e8q
+pub fn to_uci(chess_move: Move) -> String {
+    chess_move.to_string()
+}
+
+pub fn from_uci(position: &Position, uci: &str) -> Result<Move, String> {
+    let symbols: Vec<char> = uci.chars().collect();
+    if symbols.len() != 4 && symbols.len() != 5 {
+        return Err(format!("{} is not a move like e2e4", uci));
+    }
+    let (from, to) = match (parse_square(&symbols[0..2]), parse_square(&symbols[2..4])) {
+        (Some(from), Some(to)) => (from, to),
+        _ => return Err(format!("{} is not a move like e2e4", uci)),
+    };
+    let promotion = match symbols.get(4) {
+        Some(letter) => match figure_from_letter(letter.to_ascii_uppercase()) {
+            Some(figure_type) if figure_type != FigureType::Pawn && figure_type != FigureType::King => Some(figure_type),
+            _ => return Err(format!("{} promotes to an unknown figure", uci)),
+        },
+        None => None,
+    };
+    legal_moves(position)
+        .into_iter()
+        .find(|chess_move| chess_move.from == from && chess_move.to == to && chess_move.promotion == promotion)
+        .ok_or_else(|| format!("{} is not a legal move", uci))
+}
+
+fn figure_from_letter(letter: char) -> Option<FigureType> {
+    match letter {
+        'P' => Some(FigureType::Pawn),
+        'N' => Some(FigureType::Knight),
+        'B' => Some(FigureType::Bishop),
+        'R' => Some(FigureType::Fort),
+        'Q' => Some(FigureType::Queen),
+        'K' => Some(FigureType::King),
+        _ => None,
+    }
+}
+
+fn parse_square(symbols: &[char]) -> Option<(i32, i32)> {
+    match symbols {
+        [file @ 'a'..='h', rank @ '1'..='8'] => Some(((*file as u8 - b'a') as i32, (*rank as u8 - b'1') as i32)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::fen;
+
+    // every legal move written in SAN reads back as itself, in positions full of ambiguities
+    #[test]
+    fn san_round_trip() {
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+            "R6R/3Q4/1Q4Q1/4Q3/2Q4Q/Q4Q2/pp1Q4/kBNN1KB1 w - - 0 1",
+        ] {
+            let position = fen::parse(fen).unwrap();
+            for chess_move in legal_moves(&position) {
+                let san = to_san(&position, chess_move);
+                assert_eq!(from_san(&position, &san), Ok(chess_move), "{} in {}", san, fen);
+            }
+        }
+    }
+
+    #[test]
+    fn san_disambiguates_captures_and_promotes() {
+        let san = |fen: &str, uci: &str| {
+            let position = fen::parse(fen).unwrap();
+            to_san(&position, from_uci(&position, uci).unwrap())
+        };
+        assert_eq!(san("4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1", "b1d2"), "Nbd2");
+        assert_eq!(san("k7/8/8/8/8/4R3/8/4R1K1 w - - 0 1", "e1e2"), "R1e2");
+        assert_eq!(san("2k5/8/8/8/4Q2Q/K7/8/7Q w - - 0 1", "h4e1"), "Qh4e1");
+        assert_eq!(san("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1", "e4d5"), "exd5");
+        assert_eq!(san("8/P7/8/8/8/8/8/k3K3 w - - 0 1", "a7a8q"), "a8=Q+");
+        assert_eq!(san("8/P7/8/8/8/8/8/k3K3 w - - 0 1", "a7a8n"), "a8=N");
+        assert_eq!(san("rnbqkbnr/ppppp2p/8/5pp1/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 3", "d1h5"), "Qh5#");
+        assert_eq!(san("4k3/8/8/8/8/8/8/4K2R w K - 0 1", "e1g1"), "O-O");
+    }
+
+    #[test]
+    fn reading_moves_people_type() {
+        let position = Position::starting();
+        assert_eq!(from_san(&position, "Nf3").map(to_uci), Ok(String::from("g1f3")));
+        assert_eq!(from_san(&position, "e4!?").map(to_uci), Ok(String::from("e2e4")));
+        assert_eq!(from_san(&position, "Ng1f3").map(to_uci), Ok(String::from("g1f3")));
+        assert!(from_san(&position, "Nd2").is_err());
+        assert!(from_san(&position, "e5").is_err());
+        assert!(from_san(&position, "Zf3").is_err());
+
+        let position = fen::parse("4k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(from_san(&position, "a8Q").map(to_uci), Ok(String::from("a7a8q")));
+        assert!(from_san(&position, "a8").is_err());
+        assert_eq!(from_uci(&position, "a7a8r").map(|chess_move| chess_move.promotion), Ok(Some(FigureType::Fort)));
+        assert!(from_uci(&position, "a7a8k").is_err());
+        assert!(from_uci(&position, "a7a8").is_err());
+    }
+}
@@ -0,0 +1,183 @@
+use std::time::Instant;
+
+use super::{Position, Move, legal_moves};
+use crate::logger;
+
+// Counts the leaf nodes of the legal move tree, the usual way of checking a move generator.
+pub fn perft(position: &Position, depth: u32) -> u64 {
+    count_leaves(&mut position.clone(), depth)
+}
+
+// one position made and unmade all the way down, the last ply is only counted
+fn count_leaves(position: &mut Position, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let moves = legal_moves(position);
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+    let mut nodes = 0;
+    for chess_move in moves {
+        let undo = position.undo_state(chess_move);
+        position.make_move(chess_move);
+        nodes += count_leaves(position, depth - 1);
+        position.unmake_move(chess_move, &undo);
+    }
+    nodes
+}
+
+// perft split per root move, handy for finding which move a count is off by
+pub fn divide(position: &Position, depth: u32) -> Vec<(Move, u64)> {
+    legal_moves(position)
+        .into_iter()
+        .map(|chess_move| {
+            let mut after_move = position.clone();
+            after_move.make_move(chess_move);
+            (chess_move, perft(&after_move, depth.saturating_sub(1)))
+        })
+        .collect()
+}
+
+// `sahister --perft <depth>`, prints the divide of the starting position the way other engines do
+pub fn run(depth: u32) {
+    let start = Instant::now();
+    let split = divide(&Position::starting(), depth);
+    for (chess_move, nodes) in split.iter() {
+        logger::log(format!("{}: {}", chess_move, nodes));
+    }
+    let total: u64 = split.iter().map(|(_, nodes)| nodes).sum();
+    logger::log(format!("\nNodes searched: {} in {:?}", total, start.elapsed()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+    use crate::engine::{FigureType, fen};
+    use crate::engine::movegen::classic;
+
+    // reference counts from https://www.chessprogramming.org/Perft_Results
+    const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -";
+    const POSITION_3: &str = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - -";
+    const POSITION_4: &str = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq -";
+    const POSITION_5: &str = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ -";
+    const POSITION_6: &str = "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - -";
+
+    fn position(fen: &str) -> Position {
+        fen::parse(fen).expect("Reference FEN does not parse")
+    }
+
+    fn assert_counts(position: &Position, counts: &[u64]) {
+        for (depth, expected) in counts.iter().enumerate() {
+            assert_eq!(perft(position, depth as u32 + 1), *expected, "perft({})", depth + 1);
+        }
+    }
+
+    #[test]
+    fn perft_starting_position() {
+        assert_counts(&Position::starting(), &[20, 400, 8902, 197281]);
+    }
+
+    #[test]
+    fn perft_kiwipete() {
+        assert_counts(&position(KIWIPETE), &[48, 2039, 97862]);
+    }
+
+    #[test]
+    fn perft_position_3() {
+        assert_counts(&position(POSITION_3), &[14, 191, 2812, 43238]);
+    }
+
+    #[test]
+    fn perft_position_4() {
+        assert_counts(&position(POSITION_4), &[6, 264, 9467]);
+    }
+
+    #[test]
+    fn perft_position_5() {
+        assert_counts(&position(POSITION_5), &[44, 1486, 62379]);
+    }
+
+    #[test]
+    fn perft_position_6() {
+        assert_counts(&position(POSITION_6), &[46, 2079, 89890]);
+    }
+
+    #[test]
+    fn divide_adds_up_to_perft() {
+        let kiwipete = position(KIWIPETE);
+        let split = divide(&kiwipete, 2);
+        assert_eq!(split.len(), 48);
+        assert_eq!(split.iter().map(|(_, nodes)| nodes).sum::<u64>(), 2039);
+    }
+
+    #[test]
+    fn unmake_move_restores_position() {
+        for fen in [KIWIPETE, POSITION_3, POSITION_4, POSITION_5, POSITION_6] {
+            let mut position = position(fen);
+            let before = position.clone();
+            for chess_move in legal_moves(&before) {
+                let undo = position.undo_state(chess_move);
+                position.make_move(chess_move);
+                position.unmake_move(chess_move, &undo);
+                assert_eq!(position, before, "{} {}", fen, chess_move);
+            }
+        }
+    }
+
+    // the same tree walked with the old HashSet generator
+    fn classic_perft(position: &Position, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        let mut nodes = 0;
+        for (from, piece) in position.pieces() {
+            if piece.color != position.side_to_move {
+                continue;
+            }
+            for to in classic::get_legalmoves(position, from) {
+                let promotions: &[Option<FigureType>] = if position.is_promotion(from, to) {
+                    &[Some(FigureType::Queen), Some(FigureType::Fort), Some(FigureType::Bishop), Some(FigureType::Knight)]
+                } else {
+                    &[None]
+                };
+                for promotion in promotions {
+                    let mut after_move = position.clone();
+                    after_move.make_move(Move::build(position, from, to, *promotion));
+                    nodes += classic_perft(&after_move, depth - 1);
+                }
+            }
+        }
+        nodes
+    }
+
+    #[test]
+    fn bitboards_agree_with_classic_generator() {
+        for fen in [KIWIPETE, POSITION_3, POSITION_4, POSITION_5, POSITION_6] {
+            let position = position(fen);
+            assert_eq!(perft(&position, 2), classic_perft(&position, 2), "{}", fen);
+        }
+    }
+
+    // cargo test --release benchmark -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn benchmark_bitboards_against_hashsets() {
+        for (name, position, depth) in [("starting", Position::starting(), 5), ("kiwipete", position(KIWIPETE), 4)] {
+            let start = Instant::now();
+            let nodes = classic_perft(&position, depth);
+            let classic_time = start.elapsed();
+
+            let start = Instant::now();
+            assert_eq!(perft(&position, depth), nodes);
+            let bitboard_time = start.elapsed();
+
+            println!(
+                "{} perft({}) = {}: hashsets {:?}, bitboards {:?}, {:.1}x faster",
+                name, depth, nodes, classic_time, bitboard_time,
+                classic_time.as_secs_f64() / bitboard_time.as_secs_f64(),
+            );
+        }
+    }
+}
@@ -0,0 +1,507 @@
+use std::fmt;
+
+use super::{Position, fen};
+use super::notation::from_san;
+
+// PGN as in the export format of the standard: the Seven Tag Roster first, then the optional
+// tags, an empty line and the movetext wrapped below 80 columns.
+const LINE_WIDTH: usize = 79;
+
+pub struct PgnTags {
+    pub event: String,
+    pub site: String,
+    pub date: String,
+    pub round: String,
+    pub white: String,
+    pub black: String,
+    pub result: String,
+    pub time_control: Option<String>,
+    // only for games that did not start from the usual position, SetUp goes along with it
+    pub fen: Option<String>,
+    pub termination: Option<String>,
+}
+
+impl Default for PgnTags {
+    fn default() -> PgnTags {
+        PgnTags {
+            event: String::from("?"),
+            site: String::from("?"),
+            date: String::from("????.??.??"),
+            round: String::from("?"),
+            white: String::from("?"),
+            black: String::from("?"),
+            result: String::from("*"),
+            time_control: None,
+            fen: None,
+            termination: None,
+        }
+    }
+}
+
+// `movetext` has to end with the result already, like GameTree::movetext writes it
+pub fn write(tags: &PgnTags, movetext: &str) -> String {
+    let mut pgn = String::new();
+    for (name, value) in [
+        ("Event", &tags.event),
+        ("Site", &tags.site),
+        ("Date", &tags.date),
+        ("Round", &tags.round),
+        ("White", &tags.white),
+        ("Black", &tags.black),
+        ("Result", &tags.result),
+    ] {
+        pgn.push_str(&tag_pair(name, value));
+    }
+    if let Some(time_control) = &tags.time_control {
+        pgn.push_str(&tag_pair("TimeControl", time_control));
+    }
+    if let Some(fen) = &tags.fen {
+        pgn.push_str(&tag_pair("SetUp", "1"));
+        pgn.push_str(&tag_pair("FEN", fen));
+    }
+    if let Some(termination) = &tags.termination {
+        pgn.push_str(&tag_pair("Termination", termination));
+    }
+
+    pgn.push('\n');
+    let mut line = String::new();
+    for token in movetext.split_whitespace() {
+        if !line.is_empty() && line.len() + 1 + token.len() > LINE_WIDTH {
+            pgn.push_str(&line);
+            pgn.push('\n');
+            line.clear();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(token);
+    }
+    pgn.push_str(&line);
+    pgn.push_str("\n\n");
+    pgn
+}
+
+fn tag_pair(name: &str, value: &str) -> String {
+    format!("[{} \"{}\"]\n", name, value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// A game as it was read from a file. Each move keeps the lines that could have been played
+// instead of it, the comment that follows it and its numeric annotation glyphs.
+#[derive(Clone, Debug, Default)]
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    // a comment before the first move
+    pub comment: Option<String>,
+    pub moves: Vec<PgnMove>,
+    pub result: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct PgnMove {
+    pub san: String,
+    pub nags: Vec<u8>,
+    pub comment: Option<String>,
+    pub variations: Vec<Vec<PgnMove>>,
+    pub line: usize,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct PgnError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid PGN, line {}: {}", self.line, self.message)
+    }
+}
+
+impl PgnGame {
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn starting_position(&self) -> Result<Position, PgnError> {
+        match self.tag("FEN") {
+            Some(fen) => fen::parse(fen).map_err(|error| PgnError { line: 1, message: error.to_string() }),
+            None => Ok(Position::starting()),
+        }
+    }
+
+    // Plays every move of every line on the rules engine, the first one that is not legal is the error.
+    pub fn validate(&self) -> Result<(), PgnError> {
+        validate_line(self.starting_position()?, &self.moves)
+    }
+}
+
+fn validate_line(mut position: Position, moves: &[PgnMove]) -> Result<(), PgnError> {
+    for pgn_move in moves {
+        for variation in &pgn_move.variations {
+            validate_line(position.clone(), variation)?;
+        }
+        let chess_move = from_san(&position, &pgn_move.san)
+            .map_err(|message| PgnError { line: pgn_move.line, message })?;
+        position.make_move(chess_move);
+    }
+    Ok(())
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum Token {
+    Tag(String, String),
+    Comment(String),
+    Nag(u8),
+    VariationStart,
+    VariationEnd,
+    Result(String),
+    San(String),
+}
+
+// Reads all games of a file, they end with their result or at the tags of the next one.
+pub fn parse(text: &str) -> Result<Vec<PgnGame>, PgnError> {
+    let mut games = Vec::new();
+    let mut game = PgnGame::default();
+    // the main line at the bottom, the variation being read on top
+    let mut lines: Vec<Vec<PgnMove>> = vec![Vec::new()];
+    let mut pending_comment: Option<String> = None;
+    let mut in_movetext = false;
+
+    for (token, line) in tokenize(text)? {
+        let error = |message: &str| Err(PgnError { line, message: message.to_string() });
+        match token {
+            Token::Tag(name, value) => {
+                if in_movetext {
+                    if lines.len() > 1 {
+                        return error("tag inside an unclosed variation");
+                    }
+                    game.moves = lines.pop().unwrap_or_default();
+                    game.result = String::from("*");
+                    games.push(std::mem::take(&mut game));
+                    lines = vec![Vec::new()];
+                    in_movetext = false;
+                }
+                game.tags.push((name, value));
+            },
+            Token::Comment(comment) => {
+                in_movetext = true;
+                let depth = lines.len();
+                match lines.last_mut().and_then(|moves| moves.last_mut()) {
+                    Some(pgn_move) => join_comment(&mut pgn_move.comment, comment),
+                    None if depth == 1 => join_comment(&mut game.comment, comment),
+                    // in front of the first move of a variation, it goes along with that move
+                    None => join_comment(&mut pending_comment, comment),
+                }
+            },
+            Token::Nag(nag) => {
+                match lines.last_mut().and_then(|moves| moves.last_mut()) {
+                    Some(pgn_move) => pgn_move.nags.push(nag),
+                    None => return error("annotation glyph without a move"),
+                }
+            },
+            Token::VariationStart => {
+                if lines.last().is_none_or(|moves| moves.is_empty()) {
+                    return error("variation without a move to replace");
+                }
+                lines.push(Vec::new());
+            },
+            Token::VariationEnd => {
+                if lines.len() < 2 {
+                    return error("closing a variation that was never opened");
+                }
+                let variation = lines.pop().unwrap_or_default();
+                if let Some(pgn_move) = lines.last_mut().and_then(|moves| moves.last_mut()) {
+                    pgn_move.variations.push(variation);
+                }
+            },
+            Token::San(san) => {
+                in_movetext = true;
+                let mut nags = Vec::new();
+                let san = strip_suffix_annotation(&san, &mut nags);
+                if let Some(moves) = lines.last_mut() {
+                    moves.push(PgnMove {
+                        san,
+                        nags,
+                        comment: pending_comment.take(),
+                        variations: Vec::new(),
+                        line,
+                    });
+                }
+            },
+            Token::Result(result) => {
+                if lines.len() > 1 {
+                    return error("result inside an unclosed variation");
+                }
+                game.moves = lines.pop().unwrap_or_default();
+                game.result = result;
+                games.push(std::mem::take(&mut game));
+                lines = vec![Vec::new()];
+                in_movetext = false;
+            },
+        }
+    }
+
+    if lines.len() > 1 {
+        return Err(PgnError { line: text.lines().count(), message: String::from("variation is never closed") });
+    }
+    if in_movetext || !game.tags.is_empty() {
+        game.moves = lines.pop().unwrap_or_default();
+        game.result = String::from("*");
+        games.push(game);
+    }
+    Ok(games)
+}
+
+fn join_comment(comment: &mut Option<String>, text: String) {
+    match comment {
+        Some(comment) => {
+            comment.push(' ');
+            comment.push_str(&text);
+        },
+        None => *comment = Some(text),
+    }
+}
+
+// !, ?, !!, ??, !? and ?! written right after the move are the glyphs $1 to $6
+fn strip_suffix_annotation(san: &str, nags: &mut Vec<u8>) -> String {
+    let move_part = san.trim_end_matches(['!', '?']);
+    let nag = match &san[move_part.len()..] {
+        "!" => Some(1),
+        "?" => Some(2),
+        "!!" => Some(3),
+        "??" => Some(4),
+        "!?" => Some(5),
+        "?!" => Some(6),
+        _ => None,
+    };
+    nags.extend(nag);
+    move_part.to_string()
+}
+
+fn tokenize(text: &str) -> Result<Vec<(Token, usize)>, PgnError> {
+    let symbols: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut index = 0;
+    let mut line = 1;
+
+    while index < symbols.len() {
+        let symbol = symbols[index];
+        let at_line_start = index == 0 || symbols[index - 1] == '\n';
+        match symbol {
+            '\n' => {
+                line += 1;
+                index += 1;
+            },
+            _ if symbol.is_whitespace() => index += 1,
+            // escape lines are left for other programs
+            '%' if at_line_start => {
+                while index < symbols.len() && symbols[index] != '\n' {
+                    index += 1;
+                }
+            },
+            ';' => {
+                let start = index + 1;
+                while index < symbols.len() && symbols[index] != '\n' {
+                    index += 1;
+                }
+                let comment: String = symbols[start..index].iter().collect();
+                tokens.push((Token::Comment(comment.trim().to_string()), line));
+            },
+            '{' => {
+                let start_line = line;
+                let start = index + 1;
+                while index < symbols.len() && symbols[index] != '}' {
+                    if symbols[index] == '\n' {
+                        line += 1;
+                    }
+                    index += 1;
+                }
+                if index == symbols.len() {
+                    return Err(PgnError { line: start_line, message: String::from("comment is never closed") });
+                }
+                let comment: String = symbols[start..index].iter().collect();
+                tokens.push((Token::Comment(comment.split_whitespace().collect::<Vec<_>>().join(" ")), start_line));
+                index += 1;
+            },
+            '[' => {
+                let end = symbols[index..].iter()
+                    .position(|symbol| *symbol == '\n')
+                    .map_or(symbols.len(), |offset| index + offset);
+                let tag_line: String = symbols[index..end].iter().collect();
+                tokens.push((parse_tag(&tag_line, line)?, line));
+                index = end;
+            },
+            '(' => {
+                tokens.push((Token::VariationStart, line));
+                index += 1;
+            },
+            ')' => {
+                tokens.push((Token::VariationEnd, line));
+                index += 1;
+            },
+            '$' => {
+                let start = index + 1;
+                index = start;
+                while index < symbols.len() && symbols[index].is_ascii_digit() {
+                    index += 1;
+                }
+                let digits: String = symbols[start..index].iter().collect();
+                match digits.parse() {
+                    Ok(nag) => tokens.push((Token::Nag(nag), line)),
+                    Err(_) => return Err(PgnError { line, message: format!("bad annotation glyph ${}", digits) }),
+                }
+            },
+            _ => {
+                let start = index;
+                while index < symbols.len()
+                    && !symbols[index].is_whitespace()
+                    && !matches!(symbols[index], '{' | '}' | '(' | ')' | '[' | ']' | ';' | '$') {
+                    index += 1;
+                }
+                let word: String = symbols[start..index].iter().collect();
+                if index == start {
+                    return Err(PgnError { line, message: format!("unexpected '{}'", symbol) });
+                }
+                match word.as_str() {
+                    "1-0" | "0-1" | "1/2-1/2" | "*" => tokens.push((Token::Result(word), line)),
+                    _ => {
+                        // move numbers like 12. or 12... may be glued to the move, only what ends in a
+                        // dot is one so castling written as 0-0 stays
+                        let number_end = word.find(|symbol: char| !symbol.is_ascii_digit() && symbol != '.').unwrap_or(word.len());
+                        let san = match word[..number_end].rfind('.') {
+                            Some(dot) => &word[dot + 1..],
+                            None if number_end == word.len() => "",
+                            None => &word,
+                        };
+                        if !san.is_empty() {
+                            tokens.push((Token::San(san.to_string()), line));
+                        }
+                    },
+                }
+            },
+        }
+    }
+    Ok(tokens)
+}
+
+// [Name "value"], with \" and \\ inside the value
+fn parse_tag(tag_line: &str, line: usize) -> Result<Token, PgnError> {
+    let error = || PgnError { line, message: format!("bad tag {}", tag_line.trim()) };
+    let inner = tag_line.trim().strip_prefix('[').ok_or_else(error)?;
+    let name_end = inner.find(|symbol: char| symbol.is_whitespace()).ok_or_else(error)?;
+    let name = &inner[..name_end];
+    let rest = inner[name_end..].trim_start().strip_prefix('"').ok_or_else(error)?;
+
+    let mut value = String::new();
+    let mut symbols = rest.chars();
+    loop {
+        match symbols.next() {
+            Some('\\') => value.extend(symbols.next()),
+            Some('"') => break,
+            Some(symbol) => value.push(symbol),
+            None => return Err(error()),
+        }
+    }
+    if symbols.as_str().trim() != "]" {
+        return Err(error());
+    }
+    Ok(Token::Tag(name.to_string(), value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TWO_GAMES: &str = r#"[Event "First"]
+[White "Morphy, \"Paul\""]
+[Black "?"]
+[Result "1-0"]
+
+{Opening} 1. e4 e5 2. Nf3 $1 d6?! (2... Nc6 {the usual} 3. Bb5 (3. Bc4)) 3.d4
+; a line comment
+Bg4 1-0
+
+[Event "Second"]
+[Result "*"]
+
+1. Nf3 Nf6 2. g3 g6 3. Bg2 Bg7 4.0-0 0-0 *
+"#;
+
+    #[test]
+    fn reads_tags_comments_glyphs_and_variations() {
+        let games = parse(TWO_GAMES).unwrap();
+        assert_eq!(games.len(), 2);
+
+        let game = &games[0];
+        assert_eq!(game.tag("White"), Some("Morphy, \"Paul\""));
+        assert_eq!(game.result, "1-0");
+        assert_eq!(game.comment.as_deref(), Some("Opening"));
+        let sans: Vec<&str> = game.moves.iter().map(|pgn_move| pgn_move.san.as_str()).collect();
+        assert_eq!(sans, ["e4", "e5", "Nf3", "d6", "d4", "Bg4"]);
+        assert_eq!(game.moves[2].nags, [1]);
+        assert_eq!(game.moves[3].nags, [6]);
+        assert_eq!(game.moves[4].comment.as_deref(), Some("a line comment"));
+
+        let variation = &game.moves[3].variations[0];
+        assert_eq!(variation[0].san, "Nc6");
+        assert_eq!(variation[0].comment.as_deref(), Some("the usual"));
+        assert_eq!(variation[1].variations[0][0].san, "Bc4");
+        assert!(game.validate().is_ok());
+
+        assert_eq!(games[1].tag("Event"), Some("Second"));
+        let sans: Vec<&str> = games[1].moves.iter().map(|pgn_move| pgn_move.san.as_str()).collect();
+        assert_eq!(sans, ["Nf3", "Nf6", "g3", "g6", "Bg2", "Bg7", "0-0", "0-0"]);
+        assert!(games[1].validate().is_ok());
+    }
+
+    #[test]
+    fn illegal_moves_and_broken_movetext_are_errors() {
+        let game = &parse("1. e4 e5 2. Ke3 *").unwrap()[0];
+        assert_eq!(game.validate().unwrap_err().line, 1);
+
+        let game = &parse("1. e4 e5\n2. Nf3 (2. Bb5 Nf6) (2. Nf6) *").unwrap()[0];
+        assert_eq!(game.validate().unwrap_err().line, 2);
+
+        assert!(parse("1. e4 (1. d4 *").is_err());
+        assert!(parse("1. e4 {no end").is_err());
+        assert!(parse("( 1. e4 ) *").is_err());
+    }
+
+    #[test]
+    fn games_start_from_their_fen() {
+        let game = &parse("[FEN \"4k3/P7/8/8/8/8/8/4K3 w - - 0 1\"]\n\n1. a8=Q+ Kd7 *").unwrap()[0];
+        assert!(game.validate().is_ok());
+    }
+
+    #[test]
+    fn written_games_read_back() {
+        let tags = PgnTags {
+            white: String::from("Anderssen, \"Adolf\""),
+            result: String::from("1/2-1/2"),
+            fen: Some(String::from("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1")),
+            termination: Some(String::from("normal")),
+            ..Default::default()
+        };
+        // long enough to be wrapped, the comment included
+        let movetext = "1. O-O-O {the long way round, with the king going over to the queen side and the rook along} \
+            O-O $1 (1... Kf7 2. Rh7+ (2. Rxh8 Rxh8) Kg6) 2. Rh7 Rab8 3. Rdd7 Rfd8 1/2-1/2";
+        let text = write(&tags, movetext);
+        assert!(text.lines().all(|line| line.len() <= LINE_WIDTH));
+
+        let games = parse(&text).unwrap();
+        assert_eq!(games.len(), 1);
+        let game = &games[0];
+        assert_eq!(game.tag("White"), Some("Anderssen, \"Adolf\""));
+        assert_eq!(game.tag("SetUp"), Some("1"));
+        assert_eq!(game.tag("Termination"), Some("normal"));
+        assert_eq!(game.result, "1/2-1/2");
+        let sans: Vec<&str> = game.moves.iter().map(|pgn_move| pgn_move.san.as_str()).collect();
+        assert_eq!(sans, ["O-O-O", "O-O", "Rh7", "Rab8", "Rdd7", "Rfd8"]);
+        assert_eq!(game.moves[0].comment.as_deref(), Some("the long way round, with the king going over to the queen side and the rook along"));
+        assert_eq!(game.moves[1].nags, [1]);
+        assert_eq!(game.moves[1].variations[0][1].variations[0].len(), 2);
+        game.validate().unwrap();
+    }
+}
@@ -0,0 +1,341 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use super::{Position, Move, FigureType, ChessColor, legal_moves, GRID};
+
+// Iterative deepening alpha-beta with a quiescence search on captures, material and a little
+// bit of piece placement as evaluation. Scores are in centipawns from the side to move.
+pub const MATE: i32 = 30000;
+const INFINITY: i32 = 32000;
+// mate scores are counted down by the plies to the mate, anything above this is one
+const MATE_BOUND: i32 = MATE - 1000;
+const MAX_PLY: usize = 128;
+// nodes between two looks at the stop flag and the clock
+const CHECK_INTERVAL: u64 = 1024;
+
+#[derive(Clone, Copy, Default, Debug)]
+pub struct SearchLimits {
+    pub depth: Option<u32>,
+    pub movetime: Option<Duration>,
+    // keeps deepening even after a mate was found, only the stop flag ends it
+    pub infinite: bool,
+}
+
+// what a finished iteration found, the principal variation starts with the best move
+#[derive(Clone, Debug)]
+pub struct SearchInfo {
+    pub depth: u32,
+    pub score: i32,
+    pub nodes: u64,
+    pub pv: Vec<Move>,
+}
+
+impl SearchInfo {
+    pub fn best_move(&self) -> Option<Move> {
+        self.pv.first().copied()
+    }
+
+    // moves to mate, negative when the side to move gets mated
+    pub fn mate_in(&self) -> Option<i32> {
+        if self.score > MATE_BOUND {
+            Some((MATE - self.score + 1) / 2)
+        } else if self.score < -MATE_BOUND {
+            Some(-(MATE + self.score) / 2)
+        } else {
+            None
+        }
+    }
+}
+
+struct Searcher<'a> {
+    stop: &'a AtomicBool,
+    deadline: Option<Instant>,
+    nodes: u64,
+    // the node count at which should_stop looks again
+    next_check: u64,
+    aborted: bool,
+    // hashes of the positions before the current one, the game and then the search path
+    path: Vec<u64>,
+}
+
+// Searches until `limits` or `stop` end it and hands every finished depth to `report`.
+// `history` holds the hashes of the earlier positions of the game, for spotting repetitions.
+// None when there is no legal move.
+pub fn search(
+    position: &Position,
+    history: &[u64],
+    limits: SearchLimits,
+    stop: &AtomicBool,
+    mut report: impl FnMut(&SearchInfo),
+) -> Option<SearchInfo> {
+    let start = Instant::now();
+    let mut searcher = Searcher {
+        stop,
+        deadline: limits.movetime.map(|movetime| start + movetime),
+        nodes: 0,
+        next_check: 0,
+        aborted: false,
+        path: history.to_vec(),
+    };
+    let mut root_moves = legal_moves(position);
+    if root_moves.is_empty() {
+        return None;
+    }
+    order_moves(position, &mut root_moves);
+
+    let max_depth = limits.depth.unwrap_or(MAX_PLY as u32 - 1).max(1);
+    let mut best: Option<SearchInfo> = None;
+    for depth in 1..=max_depth {
+        // the best move so far goes first, that keeps the window tight from the start
+        if let Some(best_move) = best.as_ref().and_then(|info| info.best_move()) {
+            if let Some(index) = root_moves.iter().position(|chess_move| *chess_move == best_move) {
+                let best_move = root_moves.remove(index);
+                root_moves.insert(0, best_move);
+            }
+        }
+        let mut pv = Vec::new();
+        let score = searcher.root(position, &root_moves, depth, &mut pv);
+        if searcher.aborted && best.is_some() {
+            break;
+        }
+        let info = SearchInfo {
+            depth,
+            score,
+            nodes: searcher.nodes,
+            pv,
+        };
+        report(&info);
+        let found_mate = info.mate_in().is_some();
+        best = Some(info);
+        if searcher.aborted || (found_mate && !limits.infinite) {
+            break;
+        }
+    }
+    best
+}
+
+impl<'a> Searcher<'a> {
+    fn root(&mut self, position: &Position, root_moves: &[Move], depth: u32, pv: &mut Vec<Move>) -> i32 {
+        let mut alpha = -INFINITY;
+        let mut position = position.clone();
+        self.path.push(position.hash());
+        for chess_move in root_moves {
+            let undo = position.undo_state(*chess_move);
+            position.make_move(*chess_move);
+            let mut child_pv = Vec::new();
+            let score = -self.negamax(&mut position, depth - 1, 1, -INFINITY, -alpha, &mut child_pv);
+            position.unmake_move(*chess_move, &undo);
+            // a cut short first move still beats having none
+            if self.aborted && !pv.is_empty() {
+                break;
+            }
+            if score > alpha {
+                alpha = score;
+                pv.clear();
+                pv.push(*chess_move);
+                pv.extend(child_pv);
+            }
+        }
+        self.path.pop();
+        alpha
+    }
+
+    fn negamax(&mut self, position: &mut Position, depth: u32, ply: usize, mut alpha: i32, beta: i32, pv: &mut Vec<Move>) -> i32 {
+        if self.should_stop() {
+            return 0;
+        }
+        if self.is_draw(position) {
+            return 0;
+        }
+        if depth == 0 || ply >= MAX_PLY {
+            return self.quiescence(position, ply, alpha, beta);
+        }
+        self.nodes += 1;
+
+        let mut moves = legal_moves(position);
+        if moves.is_empty() {
+            return if position.is_in_check(position.side_to_move) { -MATE + ply as i32 } else { 0 };
+        }
+        order_moves(position, &mut moves);
+
+        self.path.push(position.hash());
+        for chess_move in moves {
+            let undo = position.undo_state(chess_move);
+            position.make_move(chess_move);
+            let mut child_pv = Vec::new();
+            let score = -self.negamax(position, depth - 1, ply + 1, -beta, -alpha, &mut child_pv);
+            position.unmake_move(chess_move, &undo);
+            if self.aborted {
+                break;
+            }
+            if score >= beta {
+                self.path.pop();
+                return beta;
+            }
+            if score > alpha {
+                alpha = score;
+                pv.clear();
+                pv.push(chess_move);
+                pv.extend(child_pv);
+            }
+        }
+        self.path.pop();
+        alpha
+    }
+
+    // only captures and promotions, so the evaluation never lands in the middle of an exchange
+    fn quiescence(&mut self, position: &mut Position, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+        if self.should_stop() {
+            return 0;
+        }
+        self.nodes += 1;
+        let stand_pat = evaluate(position);
+        if stand_pat >= beta || ply >= MAX_PLY {
+            return stand_pat.min(beta);
+        }
+        alpha = alpha.max(stand_pat);
+
+        let mut moves: Vec<Move> = legal_moves(position)
+            .into_iter()
+            .filter(|chess_move| chess_move.is_capture || chess_move.promotion == Some(FigureType::Queen))
+            .collect();
+        order_moves(position, &mut moves);
+        for chess_move in moves {
+            let undo = position.undo_state(chess_move);
+            position.make_move(chess_move);
+            let score = -self.quiescence(position, ply + 1, -beta, -alpha);
+            position.unmake_move(chess_move, &undo);
+            if self.aborted {
+                break;
+            }
+            if score >= beta {
+                return beta;
+            }
+            alpha = alpha.max(score);
+        }
+        alpha
+    }
+
+    fn should_stop(&mut self) -> bool {
+        // the clock is only looked at every so often, it is slow compared to a node
+        if !self.aborted && self.nodes >= self.next_check {
+            self.next_check = self.nodes + CHECK_INTERVAL;
+            self.aborted = self.stop.load(Ordering::Relaxed)
+                || self.deadline.is_some_and(|deadline| Instant::now() >= deadline);
+        }
+        self.aborted
+    }
+
+    // a single repetition is enough to call it a draw inside the search
+    fn is_draw(&self, position: &Position) -> bool {
+        if position.halfmove_clock >= 100 || position.has_insufficient_material() {
+            return true;
+        }
+        let hash = position.hash();
+        self.path.iter()
+            .rev()
+            .take(position.halfmove_clock as usize)
+            .any(|earlier| *earlier == hash)
+    }
+}
+
+pub fn figure_value(figure_type: FigureType) -> i32 {
+    match figure_type {
+        FigureType::Pawn => 100,
+        FigureType::Knight => 320,
+        FigureType::Bishop => 330,
+        FigureType::Fort => 500,
+        FigureType::Queen => 900,
+        FigureType::King => 0,
+    }
+}
+
+// material, pawns pushed forward and knights and bishops near the center
+pub fn evaluate(position: &Position) -> i32 {
+    let mut score = 0;
+    for ((col, row), piece) in position.pieces() {
+        let advance = match piece.color {
+            ChessColor::White => row,
+            ChessColor::Black => GRID - 1 - row,
+        };
+        let center_distance = (2 * col - (GRID - 1)).abs().max((2 * row - (GRID - 1)).abs());
+        let placement = match piece.figure_type {
+            FigureType::Pawn => 5 * (advance - 1),
+            FigureType::Knight | FigureType::Bishop => 4 * (GRID - 1 - center_distance),
+            _ => 0,
+        };
+        let value = figure_value(piece.figure_type) + placement;
+        score += if piece.color == position.side_to_move { value } else { -value };
+    }
+    score
+}
+
+// captures first, the most valuable victim taken by the cheapest attacker at the front
+fn order_moves(position: &Position, moves: &mut [Move]) {
+    moves.sort_by_key(|chess_move| {
+        let attacker = position.piece_at(chess_move.from).map_or(0, |piece| figure_value(piece.figure_type));
+        let victim = match chess_move.captured_square() {
+            Some(square) => position.piece_at(square).map_or(0, |piece| figure_value(piece.figure_type)),
+            None => 0,
+        };
+        let promotion = chess_move.promotion.map_or(0, figure_value);
+        let score = if chess_move.is_capture { 10 * victim - attacker + 10000 } else { 0 } + promotion;
+        -score
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{fen, notation};
+
+    fn best_move(fen: &str, depth: u32) -> (String, SearchInfo) {
+        let position = fen::parse(fen).unwrap();
+        let stop = AtomicBool::new(false);
+        let limits = SearchLimits { depth: Some(depth), ..Default::default() };
+        let info = search(&position, &[], limits, &stop, |_| {}).unwrap();
+        (notation::to_san(&position, info.best_move().unwrap()), info)
+    }
+
+    #[test]
+    fn finds_mates() {
+        let (san, info) = best_move("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 3);
+        assert_eq!(san, "Ra8#");
+        assert_eq!(info.mate_in(), Some(1));
+
+        let (san, info) = best_move("r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4", 3);
+        assert_eq!(san, "Qxf7#");
+        assert_eq!(info.mate_in(), Some(1));
+
+        // the two rooks walk the king down, Ra7 and Rb8# or the other way round
+        let (san, info) = best_move("7k/8/8/8/8/8/R7/1R4K1 w - - 0 1", 4);
+        assert!(san == "Ra7" || san == "Rb7");
+        assert_eq!(info.mate_in(), Some(2));
+    }
+
+    #[test]
+    fn takes_hanging_material() {
+        let (san, _) = best_move("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1", 3);
+        assert_eq!(san, "Rxd5");
+    }
+
+    #[test]
+    fn stalemate_and_the_stop_flag() {
+        let position = fen::parse("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert!(search(&position, &[], SearchLimits::default(), &AtomicBool::new(false), |_| {}).is_none());
+
+        // stopped before it began, it still answers after the first depth
+        let info = search(&Position::starting(), &[], SearchLimits::default(), &AtomicBool::new(true), |_| {}).unwrap();
+        assert_eq!(info.depth, 1);
+    }
+
+    #[test]
+    fn movetime_ends_the_search() {
+        // without a depth limit only the clock ends it
+        let position = fen::parse("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/2N2N2/PPPP1PPP/R1BQK2R w KQkq - 4 4").unwrap();
+        let limits = SearchLimits { movetime: Some(Duration::from_millis(50)), ..Default::default() };
+        let start = Instant::now();
+        assert!(search(&position, &[], limits, &AtomicBool::new(false), |_| {}).is_some());
+        assert!(start.elapsed() < Duration::from_millis(500), "{:?}", start.elapsed());
+    }
+}
@@ -0,0 +1,211 @@
+use std::io::{self, BufRead};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use super::{Position, ChessColor, fen};
+use super::notation::{from_uci, to_uci};
+use super::search::{self, SearchInfo, SearchLimits};
+
+// what is left on the clock is split as if this many moves were still to come
+const MOVES_TO_GO: u64 = 30;
+// kept back from every move for reading and writing the protocol
+const MOVE_OVERHEAD: Duration = Duration::from_millis(50);
+// how often a finished infinite search looks for stop
+const STOP_POLL: Duration = Duration::from_millis(5);
+
+struct Engine {
+    position: Position,
+    // hashes of the positions before the current one, for repetitions
+    history: Vec<u64>,
+    stop: Arc<AtomicBool>,
+    search: Option<JoinHandle<()>>,
+    // the running search only ends with stop
+    infinite: bool,
+}
+
+// `sahister --uci`, answers GUIs on stdin and stdout until quit or the end of the input.
+// The search runs on its own thread so stop is read while it thinks.
+pub fn run() {
+    let mut engine = Engine {
+        position: Position::starting(),
+        history: Vec::new(),
+        stop: Arc::new(AtomicBool::new(false)),
+        search: None,
+        infinite: false,
+    };
+    for line in io::stdin().lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.first().copied() {
+            Some("uci") => {
+                println!("id name Sahister {}", env!("CARGO_PKG_VERSION"));
+                println!("id author the Sahister developers");
+                println!("uciok");
+            },
+            Some("isready") => println!("readyok"),
+            Some("ucinewgame") => {
+                engine.stop_search();
+                engine.position = Position::starting();
+                engine.history.clear();
+            },
+            Some("position") => {
+                engine.stop_search();
+                if let Err(error) = engine.set_position(&words[1..]) {
+                    println!("info string {}", error);
+                }
+            },
+            Some("go") => {
+                engine.stop_search();
+                engine.go(&words[1..]);
+            },
+            Some("stop") => engine.stop_search(),
+            Some("quit") => {
+                engine.stop_search();
+                return;
+            },
+            // unknown commands are ignored, as the protocol asks
+            _ => {},
+        }
+    }
+    // scripted input ends right after go, the answer is still wanted
+    if engine.infinite {
+        engine.stop_search();
+    } else if let Some(handle) = engine.search.take() {
+        let _ = handle.join();
+    }
+}
+
+impl Engine {
+    // position [startpos | fen <fen>] [moves <move>...]
+    fn set_position(&mut self, words: &[&str]) -> Result<(), String> {
+        let moves_at = words.iter().position(|word| *word == "moves").unwrap_or(words.len());
+        let mut position = match words.first().copied() {
+            Some("startpos") => Position::starting(),
+            Some("fen") => fen::parse(&words[1..moves_at].join(" ")).map_err(|error| error.to_string())?,
+            _ => return Err(String::from("position needs startpos or fen")),
+        };
+        let mut history = Vec::new();
+        for uci in words.iter().skip(moves_at + 1) {
+            let chess_move = from_uci(&position, uci)?;
+            history.push(position.hash());
+            position.make_move(chess_move);
+        }
+        self.position = position;
+        self.history = history;
+        Ok(())
+    }
+
+    // go [depth <plies>] [movetime <ms>] [wtime <ms>] [btime <ms>] [winc <ms>] [binc <ms>] [movestogo <moves>] [infinite]
+    fn go(&mut self, words: &[&str]) {
+        let value = |name: &str| {
+            words.iter()
+                .position(|word| *word == name)
+                .and_then(|index| words.get(index + 1))
+                .and_then(|value| value.parse::<u64>().ok())
+        };
+        let (time, increment) = match self.position.side_to_move {
+            ChessColor::White => (value("wtime"), value("winc")),
+            ChessColor::Black => (value("btime"), value("binc")),
+        };
+        let clock_time = time.map(|time| {
+            let share = time / value("movestogo").unwrap_or(MOVES_TO_GO).max(1) + increment.unwrap_or(0) / 2;
+            Duration::from_millis(share.min(time)).saturating_sub(MOVE_OVERHEAD).max(Duration::from_millis(1))
+        });
+        let depth = value("depth").map(|depth| depth as u32);
+        let movetime = value("movetime")
+            .map(|movetime| Duration::from_millis(movetime).saturating_sub(MOVE_OVERHEAD).max(Duration::from_millis(1)))
+            .or(clock_time);
+        // a bare go is taken as go infinite
+        let limits = SearchLimits {
+            depth,
+            movetime,
+            infinite: words.contains(&"infinite") || (depth.is_none() && movetime.is_none()),
+        };
+
+        self.infinite = limits.infinite;
+        self.stop.store(false, Ordering::Relaxed);
+        let stop = Arc::clone(&self.stop);
+        let position = self.position.clone();
+        let history = self.history.clone();
+        let spawned = thread::Builder::new()
+            .name(String::from("search"))
+            .spawn(move || {
+                let start = Instant::now();
+                let found = search::search(&position, &history, limits, &stop, |info| {
+                    println!("{}", info_line(info, start.elapsed()));
+                });
+                // in infinite mode bestmove may only follow stop, even when the search ran out early
+                while limits.infinite && !stop.load(Ordering::Relaxed) {
+                    thread::sleep(STOP_POLL);
+                }
+                match found.and_then(|info| info.best_move()) {
+                    Some(best_move) => println!("bestmove {}", to_uci(best_move)),
+                    // mated or stalemated, the protocol has the null move for that
+                    None => println!("bestmove 0000"),
+                }
+            });
+        match spawned {
+            Ok(handle) => self.search = Some(handle),
+            Err(error) => println!("info string could not start the search: {}", error),
+        }
+    }
+
+    // bestmove is still sent by the search thread before it ends
+    fn stop_search(&mut self) {
+        if let Some(handle) = self.search.take() {
+            self.stop.store(true, Ordering::Relaxed);
+            if handle.join().is_err() {
+                println!("info string the search thread panicked");
+            }
+        }
+    }
+}
+
+fn info_line(info: &SearchInfo, elapsed: Duration) -> String {
+    let score = match info.mate_in() {
+        Some(moves) => format!("mate {}", moves),
+        None => format!("cp {}", info.score),
+    };
+    let millis = elapsed.as_millis().max(1) as u64;
+    let pv: Vec<String> = info.pv.iter().map(|chess_move| to_uci(*chess_move)).collect();
+    format!(
+        "info depth {} score {} nodes {} nps {} time {} pv {}",
+        info.depth, score, info.nodes, info.nodes * 1000 / millis, millis, pv.join(" "),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn engine() -> Engine {
+        Engine {
+            position: Position::starting(),
+            history: Vec::new(),
+            stop: Arc::new(AtomicBool::new(false)),
+            search: None,
+            infinite: false,
+        }
+    }
+
+    #[test]
+    fn position_command() {
+        let mut engine = engine();
+        engine.set_position(&["startpos", "moves", "e2e4", "e7e5", "g1f3"]).unwrap();
+        assert_eq!(fen::write(&engine.position), "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2");
+        assert_eq!(engine.history.len(), 3);
+
+        engine.set_position(&["fen", "4k3/P7/8/8/8/8/8/4K3", "w", "-", "-", "0", "1", "moves", "a7a8q"]).unwrap();
+        assert_eq!(fen::write(&engine.position), "Q3k3/8/8/8/8/8/8/4K3 b - - 0 1");
+
+        // a bad move leaves the position as it was
+        assert!(engine.set_position(&["startpos", "moves", "e2e5"]).is_err());
+        assert_eq!(fen::write(&engine.position), "Q3k3/8/8/8/8/8/8/4K3 b - - 0 1");
+        assert!(engine.set_position(&["somewhere"]).is_err());
+    }
+}
@@ -1,18 +1,33 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 
 use bevy::prelude::*;
 use crate::SkinSetResource;
-use figures::{ChessTile, Figure, ChessColor, FigureType};
+use figures::{ChessTile, Figure, FigureMetadata, ChessColor, FigureType};
 use crate::app_states::AppState;
+use crate::engine::{self, fen, notation, Position, Piece, Move, GRID};
+use crate::engine::pgn::{PgnGame, PgnMove, PgnError};
 use crate::camera_controller::CameraControllerPlugin;
 use clicker::{ClickerGamePlugin, TheTwoSelections};
+use promotion::{PromotionPlugin, PendingPromotion};
+use history::{HistoryPlugin, HistoryEntry, MovedMetadata};
+use game_tree::GameTree;
+use pgn_export::PgnExportPlugin;
+use history_view::HistoryViewPlugin;
+use move_entry::{MoveEntryPlugin, MoveEntry};
+use replay::ReplayPlugin;
 use crate::logger;
 
-const GRID: i32 = 8;
 const TILE_DIM: i32 = 32;
 
 mod figures;
 mod clicker;
+mod promotion;
+mod history;
+mod game_tree;
+mod history_view;
+mod move_entry;
+pub mod replay;
+pub mod pgn_export;
 
 pub struct GamePlugin;
 
@@ -21,10 +36,16 @@ impl Plugin for GamePlugin {
         app
             .add_plugin(CameraControllerPlugin)
             .add_plugin(ClickerGamePlugin)
+            .add_plugin(PromotionPlugin)
+            .add_plugin(HistoryPlugin)
+            .add_plugin(HistoryViewPlugin)
+            .add_plugin(PgnExportPlugin)
+            .add_plugin(ReplayPlugin)
+            .add_plugin(MoveEntryPlugin)
             .add_event::<LegalMoveEvent>()
-            .insert_resource(CurrentPlayer {
-                color: Some(ChessColor::White),
-            })
+            .add_event::<CheckEvent>()
+            .add_event::<TurnChangedEvent>()
+            .insert_resource(CurrentPlayer::new())
             .add_system_set(
                 SystemSet::on_enter(AppState::InGame)
                 .with_system(setup_ingame)
@@ -33,8 +54,16 @@ impl Plugin for GamePlugin {
                 SystemSet::on_update(AppState::InGame)
                 .with_system(update_ingame)
                 .with_system(move_legality)
-                .with_system(move_figures)
+                .with_system(move_figures.label(GameSystem::MoveFigures))
+                .with_system(end_turn.label(GameSystem::EndTurn).after(GameSystem::MoveFigures))
+                .with_system(announce_turn.after(GameSystem::EndTurn))
+                .with_system(check_check)
+                .with_system(check_game_over)
+                .with_system(claim_draw)
+                .with_system(log_fen)
             )
+            // after the commands of the update stage are applied, so despawns are done
+            .add_system_to_stage(CoreStage::PostUpdate, check_figures_mirror_game)
             .add_system_set(
                 SystemSet::on_exit(AppState::InGame)
                 .with_system(exit_ingame)
@@ -48,15 +77,245 @@ pub struct CurrentSkinSet {
     figures: HashMap<String, Handle<Image>>,
 }
 
+// what setup_ingame puts on the board, the usual starting position if no FEN is given,
+// a game read from a PGN file is set up at its start for replaying
 #[derive(Default)]
+pub struct StartingPosition {
+    pub fen: Option<String>,
+    pub pgn: Option<PgnGame>,
+}
+
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+enum GameSystem {
+    MoveFigures,
+    EndTurn,
+}
+
+// whose turn it is, kept in step with ChessGame by end_turn
+#[derive(Default, PartialEq, Eq)]
 struct CurrentPlayer {
     color: Option<ChessColor>,
+    fullmove_number: u32,
+}
+
+impl CurrentPlayer {
+    fn new() -> CurrentPlayer {
+        CurrentPlayer {
+            color: Some(ChessColor::White),
+            fullmove_number: 1,
+        }
+    }
+
+    fn from_position(position: &Position) -> CurrentPlayer {
+        CurrentPlayer {
+            color: Some(position.side_to_move),
+            fullmove_number: position.fullmove_number,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum GameOverReason {
+    Checkmate,
+    Stalemate,
+    InsufficientMaterial,
+    ThreefoldRepetition,
+    FivefoldRepetition,
+    FiftyMoveRule,
+    SeventyFiveMoveRule,
+}
+
+impl GameOverReason {
+    pub fn get_str(&self) -> &str {
+        match self {
+            Self::Checkmate => {"CHECKMATE"},
+            Self::Stalemate => {"STALEMATE"},
+            Self::InsufficientMaterial => {"INSUFFICIENT MATERIAL"},
+            Self::ThreefoldRepetition => {"THREEFOLD REPETITION"},
+            Self::FivefoldRepetition => {"FIVEFOLD REPETITION"},
+            Self::FiftyMoveRule => {"FIFTY MOVE RULE"},
+            Self::SeventyFiveMoveRule => {"75 MOVE RULE"},
+        }
+    }
+}
+
+pub struct GameResult {
+    pub winner: Option<ChessColor>,
+    pub reason: GameOverReason,
+}
+
+// the rules side of the game, figure entities just mirror what is in here
+pub struct ChessGame {
+    pub position: Position,
+    // hashes along the line from the start to the current position
+    repetitions: Vec<u64>,
+    tree: GameTree,
+}
+
+impl ChessGame {
+    fn new(position: Position) -> ChessGame {
+        ChessGame {
+            repetitions: vec![position.hash()],
+            tree: GameTree::new(position.clone()),
+            position,
+        }
+    }
+
+    // the whole tree of a read game with its comments, left at the starting position
+    fn from_pgn(pgn_game: &PgnGame) -> Result<ChessGame, PgnError> {
+        let mut game = ChessGame::new(pgn_game.starting_position()?);
+        game.tree.annotate(pgn_game.comment.clone(), Vec::new());
+        game.load_line(&pgn_game.moves)?;
+        game.tree.rewind();
+        game.replay();
+        Ok(game)
+    }
+
+    // the main move goes in first so it stays the first child, its variations branch off after
+    fn load_line(&mut self, moves: &[PgnMove]) -> Result<(), PgnError> {
+        for pgn_move in moves {
+            let before = self.tree.current();
+            self.play_pgn_move(pgn_move)?;
+            if pgn_move.variations.is_empty() {
+                continue;
+            }
+            let after = self.tree.current();
+            for variation in &pgn_move.variations {
+                self.tree.jump_to(before);
+                self.replay();
+                self.load_line(variation)?;
+            }
+            self.tree.jump_to(after);
+            self.replay();
+        }
+        Ok(())
+    }
+
+    fn play_pgn_move(&mut self, pgn_move: &PgnMove) -> Result<(), PgnError> {
+        let chess_move = notation::from_san(&self.position, &pgn_move.san)
+            .map_err(|message| PgnError { line: pgn_move.line, message })?;
+        let counts = self.number_of_moves();
+        let count = |square: Option<(i32, i32)>| square.map_or(0, |square| counts[(square.1 * GRID + square.0) as usize]);
+        let metadata = MovedMetadata {
+            mover: count(Some(chess_move.from)),
+            rook: count(chess_move.castling_rook_move().map(|(rook_from, _)| rook_from)),
+            captured: count(chess_move.captured_square()),
+        };
+        self.make_move(chess_move, metadata);
+        self.tree.annotate(pgn_move.comment.clone(), pgn_move.nags.clone());
+        Ok(())
+    }
+
+    // zobrist key of the current position, polyglot compatible
+    pub fn hash(&self) -> u64 {
+        self.position.hash()
+    }
+
+    fn make_move(&mut self, chess_move: Move, metadata: MovedMetadata) -> Option<Piece> {
+        let undo = self.position.undo_state(chess_move);
+        let captured = self.position.make_move(chess_move);
+        self.repetitions.push(self.hash());
+        self.tree.add_move(HistoryEntry {
+            chess_move,
+            undo,
+            metadata,
+        });
+        captured
+    }
+
+    fn undo(&mut self) -> Option<HistoryEntry> {
+        let entry = self.tree.back()?;
+        self.position.unmake_move(entry.chess_move, &entry.undo);
+        self.repetitions.pop();
+        Some(entry)
+    }
+
+    fn next_redo(&self) -> Option<Move> {
+        self.tree.next_redo()
+    }
+
+    fn ply_count(&self) -> usize {
+        self.repetitions.len() - 1
+    }
+
+    // the position after the first `ply` moves, the game itself stays where it is
+    fn position_at(&self, ply: usize) -> Position {
+        let mut position = self.position.clone();
+        for entry in self.tree.path(self.tree.current())[ply..].iter().rev() {
+            position.unmake_move(entry.chess_move, &entry.undo);
+        }
+        position
+    }
+
+    // makes the position `ply` moves in the live one, the moves after it stay in the tree
+    fn go_back_to(&mut self, ply: usize) {
+        let node = self.tree.ancestor_at(ply);
+        self.tree.jump_to(node);
+        self.replay();
+    }
+
+    fn promote_variation(&mut self) -> bool {
+        self.tree.promote_variation()
+    }
+
+    fn delete_variation(&mut self) -> bool {
+        let deleted = self.tree.delete_variation();
+        if deleted {
+            self.replay();
+        }
+        deleted
+    }
+
+    // PGN movetext of everything played, variations included
+    fn movetext(&self) -> String {
+        self.tree.movetext("*")
+    }
+
+    // FigureMetadata::number_of_moves for every square, by following the figures along the line
+    fn number_of_moves(&self) -> [u16; 64] {
+        let mut counts = [0; 64];
+        let index = |square: (i32, i32)| (square.1 * GRID + square.0) as usize;
+        for entry in self.tree.path(self.tree.current()) {
+            let chess_move = entry.chess_move;
+            if let Some((rook_from, rook_to)) = chess_move.castling_rook_move() {
+                counts[index(rook_to)] = counts[index(rook_from)] + 1;
+                counts[index(rook_from)] = 0;
+            }
+            if let Some(captured) = chess_move.captured_square() {
+                counts[index(captured)] = 0;
+            }
+            counts[index(chess_move.to)] = counts[index(chess_move.from)] + 1;
+            counts[index(chess_move.from)] = 0;
+        }
+        counts
+    }
+
+    fn en_passant_pawn(&self) -> Option<(i32, i32)> {
+        figures::en_passant_pawn(&self.position)
+    }
+
+    // sets the position up again from the start along the line to the current node
+    fn replay(&mut self) {
+        self.position = self.tree.start.clone();
+        self.repetitions = vec![self.position.hash()];
+        for entry in self.tree.path(self.tree.current()) {
+            self.position.make_move(entry.chess_move);
+            self.repetitions.push(self.position.hash());
+        }
+    }
+
+    // how many times the current position has been on the board
+    fn repetition_count(&self) -> usize {
+        let current = self.hash();
+        self.repetitions.iter().filter(|hash| **hash == current).count()
+    }
 }
 
 fn setup_ingame(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     skin_set: Res<SkinSetResource>,
+    starting_position: Res<StartingPosition>,
 ) {
     logger::log("Creating chessboard");
     let current_skins = load_current_skins(
@@ -69,12 +328,30 @@ fn setup_ingame(
         &current_skins
     );
 
+    let position = match &starting_position.fen {
+        Some(starting_fen) => fen::parse(starting_fen).unwrap_or_else(|error| {
+            logger::log(error);
+            Position::starting()
+        }),
+        None => Position::starting(),
+    };
+    let game = match &starting_position.pgn {
+        Some(pgn_game) => ChessGame::from_pgn(pgn_game).unwrap_or_else(|error| {
+            logger::log(error);
+            ChessGame::new(position)
+        }),
+        None => ChessGame::new(position),
+    };
     add_figures(
         &mut commands,
-        &current_skins
+        &current_skins,
+        &game.position
     );
 
     commands.insert_resource(current_skins);
+    commands.insert_resource(TheTwoSelections::default());
+    commands.insert_resource(CurrentPlayer::from_position(&game.position));
+    commands.insert_resource(game);
 }
 
 fn update_ingame(
@@ -83,14 +360,52 @@ fn update_ingame(
 
 }
 
+// Debug builds rebuild the position out of the figure entities whenever the game changed and
+// log where it differs from the model. Castling rights come from FigureMetadata::number_of_moves,
+// so the model may have given up a right the untouched figures would still allow, not the other way.
+// En passant comes from FigureMetadata::is_enpassant_valid.
+fn check_figures_mirror_game(
+    game: Option<Res<ChessGame>>,
+    figures: Query<(&Figure, &FigureMetadata)>,
+) {
+    if !cfg!(debug_assertions) {
+        return;
+    }
+    let game = match game {
+        Some(game) if game.is_changed() => game,
+        _ => return,
+    };
+    let mirrored = figures::position_from_figures(figures.iter(), game.position.side_to_move);
+    for row in 0..GRID {
+        for col in 0..GRID {
+            if mirrored.piece_at((col, row)) != game.position.piece_at((col, row)) {
+                logger::log(format!("Figures and game disagree on {}", engine::square_name((col, row))));
+            }
+        }
+    }
+    for color in [ChessColor::White, ChessColor::Black] {
+        if (game.position.castling.king_side(color) && !mirrored.castling.king_side(color))
+            || (game.position.castling.queen_side(color) && !mirrored.castling.queen_side(color)) {
+            logger::log(format!("Figures and game disagree on castling for {}", color.get_str().to_lowercase()));
+        }
+    }
+    if mirrored.en_passant != game.position.en_passant {
+        logger::log("Figures and game disagree on en passant");
+    }
+}
+
 fn exit_ingame(
     mut commands: Commands,
     tiles_query: Query<Entity, With<ChessTile>>,
+    figures_query: Query<Entity, With<Figure>>,
 ) {
     logger::log("Destroying chessboard");
     for tile_entity in tiles_query.iter() {
         commands.entity(tile_entity).despawn();
     }
+    for figure_entity in figures_query.iter() {
+        commands.entity(figure_entity).despawn();
+    }
 }
 
 fn load_current_skins(
@@ -154,8 +469,9 @@ fn construct_chessboard(
 fn add_figures(
     commands: &mut Commands,
     current_skins: &CurrentSkinSet,
+    position: &Position,
 ) {
-    figures::get_figures(commands, current_skins);
+    figures::get_figures(commands, current_skins, position);
 }
 
 fn transform_grid_to_world(col: i32, row: i32, tile_dim: i32, z: f32) -> Vec3 {
@@ -166,54 +482,34 @@ fn transform_grid_to_world(col: i32, row: i32, tile_dim: i32, z: f32) -> Vec3 {
 fn move_legality(
     mut selection: ResMut<TheTwoSelections>,
     mut legalmove_event: EventWriter<LegalMoveEvent>,
-    current_player: Res<CurrentPlayer>,
-    figures: Query<&Figure>,
+    game: Res<ChessGame>,
+    mut pending_promotion: ResMut<PendingPromotion>,
 ) {    
+    if pending_promotion.legal_move.is_some() {
+        // board is frozen until the promotion figure is picked
+        selection.selection1 = None;
+        selection.selection2 = None;
+        return;
+    }
     if selection.selection1.is_some() && selection.selection2.is_some() {
-        let mut move_is_legal = false;
         let (selection1, selection2) = (selection.selection1.take(), selection.selection2.take());
-        let (tile1, figure1) = selection1.unwrap();
-        let (tile2, figure2) = selection2.unwrap();
-    
-        let mut king_tile = None;
-        for fig in figures.iter() {
-            if fig.figure_type == FigureType::King && fig.color == current_player.color.unwrap() {
-                king_tile = Some(*fig);
-            }
-        }
-    
-        let figure1 = figure1.unwrap();
-        let legal_moves = get_legalmoves(figure1.color, figure1.figure_type, tile1);
-        if legal_moves.contains(&(tile2.col, tile2.row)) {
-            move_is_legal = true;
-        }
-        match figure1.figure_type {
-            FigureType::Pawn => {
-    
+        let (tile1, _) = selection1.unwrap();
+        let (tile2, _) = selection2.unwrap();
+
+        // a promotion shows up once per figure, the popup picks which one is played
+        let legal_moves: Vec<Move> = engine::get_legalmoves(&game.position, tile1.square())
+            .into_iter()
+            .filter(|chess_move| chess_move.to == tile2.square())
+            .collect();
+        match legal_moves.as_slice() {
+            [] => {},
+            [chess_move] => {
+                legalmove_event.send(LegalMoveEvent::build(&game.position, *chess_move));
             },
-            FigureType::Fort => {
-    
-            },
-            FigureType::Bishop => {
-    
-            },
-            FigureType::King => {
-    
-            },
-            FigureType::Knight => {
-    
-            },
-            FigureType::Queen => {
-    
+            [chess_move, ..] => {
+                pending_promotion.legal_move = Some(LegalMoveEvent::build(&game.position, *chess_move));
             }
         }
-    
-        if move_is_legal {
-            legalmove_event.send(LegalMoveEvent{
-                move_to: (tile2.col, tile2.row),
-                figure: figure1,
-            });
-        }
     }
 }
 
@@ -224,143 +520,277 @@ fn is_tile_free(
     return selection.selection2.unwrap().1.is_none();
 }
 
-fn get_legalmoves(
-    figure_color: ChessColor,
-    figure_type: FigureType,
-    tile: ChessTile,
-) -> HashSet<(i32, i32)> {
-    let mut set_moves = HashSet::new();
-    match figure_type {
-        FigureType::Pawn => {
-            let dir: i32 = match figure_color {
-                ChessColor::Black => {
-                    -1
-                },
-                ChessColor::White => {
-                    1
-                }
-            };
-            set_moves.insert((tile.col, tile.row + dir)); 
-            set_moves.insert((tile.col, tile.row + 2 * dir));
-            set_moves.insert((tile.col - 1, tile.row + dir));
-            set_moves.insert((tile.col + 1, tile.row + dir));
-        },
-        FigureType::Fort => {
-            let start_pos = (tile.col, tile.row);
-            for r in 0..GRID {
-                if r != start_pos.1 {
-                    set_moves.insert((start_pos.0, r));
-                }
-            }
-            for c in 0..GRID {
-                if c != start_pos.0 {
-                    set_moves.insert((c, start_pos.1));
-                }
-            }
-        },
-        FigureType::Bishop => {
-            let start_pos = (tile.col, tile.row);
-            for offset in 0..GRID {
-                set_moves.insert((start_pos.0 + offset, start_pos.1 + offset)); // right diag up
-                set_moves.insert((start_pos.0 + offset, start_pos.1 - offset)); // right diag down
-                set_moves.insert((start_pos.0 - offset, start_pos.1 + offset)); // left diag up
-                set_moves.insert((start_pos.0 - offset, start_pos.1 - offset)); // left diag down
-            }
-        },
-        FigureType::Knight => {
-            let start_pos = (tile.col, tile.row);
-            set_moves.insert((start_pos.0 + 2, start_pos.1 + 1));
-            set_moves.insert((start_pos.0 + 2, start_pos.1 - 1));
-            set_moves.insert((start_pos.0 - 2, start_pos.1 + 1));
-            set_moves.insert((start_pos.0 - 2, start_pos.1 - 1));
-
-            set_moves.insert((start_pos.0 + 1, start_pos.1 + 2));
-            set_moves.insert((start_pos.0 - 1, start_pos.1 + 2));
-            set_moves.insert((start_pos.0 + 1, start_pos.1 - 2));
-            set_moves.insert((start_pos.0 - 1, start_pos.1 - 2));
-        },
-        FigureType::King => {
-            let start_pos = (tile.col, tile.row);
-            for offset_x in -1..=1 {
-                for offset_y in -1..=1 {
-                    if offset_x != 0 || offset_y != 0 {
-                        set_moves.insert((start_pos.0 + offset_x, start_pos.1 + offset_y));
-                    }
-                }   
-            }
-        },
-        FigureType::Queen => {
-            let start_pos = (tile.col, tile.row);
-            for r in 0..GRID {
-                if r != start_pos.1 {
-                    set_moves.insert((start_pos.0, r));
-                }
-            }
-            for c in 0..GRID {
-                if c != start_pos.0 {
-                    set_moves.insert((c, start_pos.1));
-                }
-            }
-            for offset in 0..GRID {
-                set_moves.insert((start_pos.0 + offset, start_pos.1 + offset)); // right diag up
-                set_moves.insert((start_pos.0 + offset, start_pos.1 - offset)); // right diag down
-                set_moves.insert((start_pos.0 - offset, start_pos.1 + offset)); // left diag up
-                set_moves.insert((start_pos.0 - offset, start_pos.1 - offset)); // left diag down
-            }
-        }
-    }
-    set_moves
-}
-
 fn is_tile_underattack(
-    tile: ChessTile,
-    figures: Query<&Figure>,
+    tile: &ChessTile,
+    position: &Position,
+    attacker: ChessColor,
 ) -> bool {
-    true
+    engine::is_square_attacked(position, tile.square(), attacker)
 }
 
 
 
 fn check_nolegal_move(
-
+    position: &Position,
 ) -> bool {
-    true
+    !engine::has_legal_move(position)
 }
 
 fn check_check(
-
+    game: Res<ChessGame>,
+    tiles: Query<&ChessTile>,
+    mut check_event: EventWriter<CheckEvent>,
 ) {
-
+    if !game.is_changed() {
+        return;
+    }
+    let color = game.position.side_to_move;
+    let king = match game.position.find_king(color) {
+        Some(king) => king,
+        None => return,
+    };
+    for tile in tiles.iter() {
+        if tile.square() == king && is_tile_underattack(tile, &game.position, color.opposite()) {
+            check_event.send(CheckEvent {
+                color,
+                king_tile: *tile,
+            });
+        }
+    }
 }
 
 fn check_checkmate(
+    position: &Position,
+) -> Option<GameResult> {
+    if !check_nolegal_move(position) {
+        return None;
+    }
+    let color = position.side_to_move;
+    if position.is_in_check(color) {
+        Some(GameResult {
+            winner: Some(color.opposite()),
+            reason: GameOverReason::Checkmate,
+        })
+    } else {
+        Some(GameResult {
+            winner: None,
+            reason: GameOverReason::Stalemate,
+        })
+    }
+}
 
+// draws that end the game on their own, nobody has to claim them
+fn check_draw(
+    game: &ChessGame,
+) -> Option<GameResult> {
+    let reason = if game.position.has_insufficient_material() {
+        GameOverReason::InsufficientMaterial
+    } else if game.repetition_count() >= 5 {
+        GameOverReason::FivefoldRepetition
+    } else if game.position.halfmove_clock >= 150 {
+        GameOverReason::SeventyFiveMoveRule
+    } else {
+        return None;
+    };
+    Some(GameResult {
+        winner: None,
+        reason,
+    })
+}
+
+fn claimable_draw(
+    game: &ChessGame,
+) -> Option<GameOverReason> {
+    if game.repetition_count() >= 3 {
+        Some(GameOverReason::ThreefoldRepetition)
+    } else if game.position.halfmove_clock >= 100 {
+        Some(GameOverReason::FiftyMoveRule)
+    } else {
+        None
+    }
+}
+
+fn check_game_over(
+    mut commands: Commands,
+    game: Res<ChessGame>,
+    app_state: ResMut<State<AppState>>,
 ) {
+    if !game.is_changed() {
+        return;
+    }
+    // mate on the very last move still wins, so it goes before the draws
+    match check_checkmate(&game.position).or_else(|| check_draw(&game)) {
+        Some(result) => {
+            end_game(&mut commands, app_state, result);
+        },
+        None => {
+            if let Some(reason) = claimable_draw(&game) {
+                logger::log(format!("Draw by {} can be claimed, press D", reason.get_str()));
+            }
+        }
+    }
+}
 
+fn claim_draw(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    game: Res<ChessGame>,
+    move_entry: Res<MoveEntry>,
+    app_state: ResMut<State<AppState>>,
+) {
+    if keys.just_pressed(KeyCode::D) && !move_entry.is_typing() {
+        if let Some(reason) = claimable_draw(&game) {
+            end_game(&mut commands, app_state, GameResult {
+                winner: None,
+                reason,
+            });
+        }
+    }
+}
+
+// F prints the live position as FEN
+fn log_fen(
+    keys: Res<Input<KeyCode>>,
+    game: Res<ChessGame>,
+    move_entry: Res<MoveEntry>,
+) {
+    if keys.just_pressed(KeyCode::F) && !move_entry.is_typing() {
+        logger::log(fen::write(&game.position));
+    }
+}
+
+fn end_game(
+    commands: &mut Commands,
+    mut app_state: ResMut<State<AppState>>,
+    result: GameResult,
+) {
+    logger::log(result.reason.get_str());
+    commands.insert_resource(result);
+
+    // pushed on top so the final position stays on the board behind the game over screen
+    match app_state.push(AppState::GameOver) {
+        Ok(_) => {},
+        Err(msg) => {
+            logger::log(msg);
+        }
+    }
 }
 
 fn move_figures(
+    mut commands: Commands,
     mut legal_move_event: EventReader<LegalMoveEvent>,
-    mut figures: Query<(&mut Transform, &mut Figure)>
+    mut game: ResMut<ChessGame>,
+    current_skins: Res<CurrentSkinSet>,
+    mut figures: Query<(Entity, &mut Transform, &mut Handle<Image>, &mut Figure, &mut FigureMetadata)>
 ) {
     for move_event in legal_move_event.iter() {
-        for (mut transform, mut fig) in figures.iter_mut() {
-            if move_event.figure == *fig {
-                transform.translation = transform_grid_to_world(move_event.move_to.0, move_event.move_to.1, TILE_DIM, 0.0);
-                fig.col = move_event.move_to.0;
-                fig.row = move_event.move_to.1;
+        let chess_move = move_event.chess_move;
+        let mover = match game.position.piece_at(chess_move.from) {
+            Some(piece) => Figure::from_piece(piece, chess_move.from),
+            None => continue,
+        };
+        let captured_square = chess_move.captured_square();
+        let castling_rook = chess_move.castling_rook_move();
+        let number_of_moves = |square: Option<(i32, i32)>| {
+            figures.iter()
+                .find(|(_, _, _, fig, _)| Some(fig.square()) == square)
+                .map_or(0, |(_, _, _, _, metadata)| metadata.number_of_moves)
+        };
+        let metadata = MovedMetadata {
+            mover: number_of_moves(Some(chess_move.from)),
+            rook: number_of_moves(castling_rook.map(|(rook_from, _)| rook_from)),
+            captured: number_of_moves(captured_square),
+        };
+        logger::log(format!("{} {} ({})", mover.get_name(), notation::to_san(&game.position, chess_move), notation::to_uci(chess_move)));
+        game.make_move(chess_move, metadata);
+        // entities are found by the square they stand on before the move
+        for (entity, mut transform, mut texture, mut fig, mut metadata) in figures.iter_mut() {
+            let square = fig.square();
+            if square == chess_move.from {
+                transform.translation = transform_grid_to_world(chess_move.to.0, chess_move.to.1, TILE_DIM, 0.0);
+                fig.col = chess_move.to.0;
+                fig.row = chess_move.to.1;
+                metadata.number_of_moves += 1;
+                metadata.is_enpassant_valid = fig.figure_type == FigureType::Pawn
+                    && (chess_move.to.1 - chess_move.from.1).abs() == 2;
+                if let Some(promotion) = chess_move.promotion {
+                    fig.figure_type = promotion;
+                    *texture = current_skins.figures[&fig.get_name()].clone();
+                }
+            } else if Some(square) == captured_square {
+                if let Some(captured) = move_event.captured {
+                    logger::log(format!("{} takes {}", mover.get_name(), Figure::from_piece(captured, square).get_name()));
+                }
+                commands.entity(entity).despawn();
+            } else if let Some((_, rook_to)) = castling_rook.filter(|(rook_from, _)| *rook_from == square) {
+                transform.translation = transform_grid_to_world(rook_to.0, rook_to.1, TILE_DIM, 0.0);
+                fig.col = rook_to.0;
+                fig.row = rook_to.1;
+                metadata.number_of_moves += 1;
+                metadata.is_enpassant_valid = false;
+            } else {
+                // en passant expires after one ply
+                metadata.is_enpassant_valid = false;
             }
         }
     }
 }
 
+// The turn is read off the position rather than flipped per event, so moves that were skipped
+// don't count and undo, redo and variations hand it back the same way.
 fn end_turn(
+    game: Res<ChessGame>,
+    mut current_player: ResMut<CurrentPlayer>,
+    mut turn_changed_event: EventWriter<TurnChangedEvent>,
+) {
+    if !game.is_changed() {
+        return;
+    }
+    let player = CurrentPlayer::from_position(&game.position);
+    if player == *current_player {
+        return;
+    }
+    *current_player = player;
+    turn_changed_event.send(TurnChangedEvent {
+        color: game.position.side_to_move,
+        fullmove_number: current_player.fullmove_number,
+    });
+}
 
+fn announce_turn(
+    mut turn_changed_event: EventReader<TurnChangedEvent>,
+    mut check_event: EventReader<CheckEvent>,
 ) {
+    for turn in turn_changed_event.iter() {
+        logger::log(format!("Move {}, {} to play", turn.fullmove_number, turn.color.get_str()));
+    }
+    for check in check_event.iter() {
+        let tile = check.king_tile;
+        logger::log(format!("{} is in check, king on {}{}", check.color.get_str(), (b'a' + tile.col as u8) as char, tile.row + 1));
+    }
+}
 
+pub struct TurnChangedEvent {
+    pub color: ChessColor,
+    pub fullmove_number: u32,
 }
 
-struct LegalMoveEvent {
-    move_to: (i32, i32),
-    figure: Figure,
+pub struct CheckEvent {
+    pub color: ChessColor,
+    pub king_tile: ChessTile,
+}
+
+pub struct LegalMoveEvent {
+    pub chess_move: Move,
+    pub captured: Option<Piece>,
+}
+
+impl LegalMoveEvent {
+    // `position` is the one the move is about to be played in
+    pub fn build(position: &Position, chess_move: Move) -> LegalMoveEvent {
+        LegalMoveEvent {
+            chess_move,
+            captured: chess_move.captured_square().and_then(|square| position.piece_at(square)),
+        }
+    }
 }
\ No newline at end of file
@@ -1,7 +1,11 @@
+use std::marker::PhantomData;
+
 use bevy::prelude::*;
-use super::figures::{ChessTile, Figure, ChessColor};
+use bevy::ecs::system::SystemParam;
+use super::figures::{ChessTile, Figure};
 use crate::app_states::AppState;
 use super::CurrentPlayer;
+use super::history_view::HistoryView;
 use crate::camera_controller::{self, MainCamera};
 
 pub struct ClickerGamePlugin;
@@ -32,6 +36,15 @@ impl TheTwoSelections {
     }
 }
 
+// who may pick a figure, nobody while earlier positions are looked at
+#[derive(SystemParam)]
+struct Turn<'w, 's> {
+    current_player: Res<'w, CurrentPlayer>,
+    history_view: Res<'w, HistoryView>,
+    #[system_param(ignore)]
+    marker: PhantomData<&'s ()>,
+}
+
 fn click_tile(
     windows: Res<Windows>,
     input: Res<Input<MouseButton>>,
@@ -39,8 +52,12 @@ fn click_tile(
     camera: Query<&Transform, With<MainCamera>>,
     tiles: Query<(&Transform, &ChessTile)>,
     figures: Query<&Figure>,
-    current_player: Res<CurrentPlayer>,
+    turn: Turn,
 ) {
+    // only the live position can be played on
+    if turn.history_view.is_viewing() {
+        return;
+    }
     if input.just_pressed(MouseButton::Left) {
         let window = windows.get_primary().expect("No primary window");
         let camera_transform = camera.single();
@@ -60,7 +77,7 @@ fn click_tile(
                     &mut selections,
                     *clicked_tile.unwrap(),
                     figure,
-                    current_player
+                    turn.current_player
                 );
             } else {
                 reset_selection(&mut selections);
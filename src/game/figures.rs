@@ -1,6 +1,8 @@
 use bevy::prelude::*;
 
 use super::CurrentSkinSet;
+use crate::engine::{Position, Piece, CastlingRights};
+pub use crate::engine::{FigureType, ChessColor};
 
 
 #[derive(Component, Clone, Copy, PartialEq, Eq)]
@@ -12,56 +14,20 @@ pub struct Figure {
 }
 
 impl Figure {
-    pub fn build_b_pawn(pos: (i32, i32)) -> Figure {
-        Figure::build(FigureType::Pawn, ChessColor::Black, pos)
-    }
-
-    pub fn build_b_fort(pos: (i32, i32)) -> Figure {
-        Figure::build(FigureType::Fort, ChessColor::Black, pos)
-    }
-
-    pub fn build_b_knight(pos: (i32, i32)) -> Figure {
-        Figure::build(FigureType::Knight, ChessColor::Black, pos)
-    }
-
-    pub fn build_b_bishop(pos: (i32, i32)) -> Figure {
-        Figure::build(FigureType::Bishop, ChessColor::Black, pos)
-    }
-
-    pub fn build_b_king(pos: (i32, i32)) -> Figure {
-        Figure::build(FigureType::King, ChessColor::Black, pos)
-    }
-    
-    pub fn build_b_queen(pos: (i32, i32)) -> Figure {
-        Figure::build(FigureType::Queen, ChessColor::Black, pos)
-    }
-
-    pub fn build_w_pawn(pos: (i32, i32)) -> Figure {
-        Figure::build(FigureType::Pawn, ChessColor::White, pos)
-    }
-
-    pub fn build_w_fort(pos: (i32, i32)) -> Figure {
-        Figure::build(FigureType::Fort, ChessColor::White, pos)
-    }
-
-    pub fn build_w_knight(pos: (i32, i32)) -> Figure {
-        Figure::build(FigureType::Knight, ChessColor::White, pos)
+    pub fn get_name(&self) -> String {
+        format!("{}_{}", self.color.get_str(), self.figure_type.get_str())
     }
 
-    pub fn build_w_bishop(pos: (i32, i32)) -> Figure {
-        Figure::build(FigureType::Bishop, ChessColor::White, pos)
+    pub fn from_piece(piece: Piece, square: (i32, i32)) -> Figure {
+        Figure::build(piece.figure_type, piece.color, square)
     }
 
-    pub fn build_w_king(pos: (i32, i32)) -> Figure {
-        Figure::build(FigureType::King, ChessColor::White, pos)
-    }
-    
-    pub fn build_w_queen(pos: (i32, i32)) -> Figure {
-        Figure::build(FigureType::Queen, ChessColor::White, pos)
+    pub fn piece(&self) -> Piece {
+        Piece::build(self.figure_type, self.color)
     }
 
-    pub fn get_name(&self) -> String {
-        format!("{}_{}", self.color.get_str(), self.figure_type.get_str())
+    pub fn square(&self) -> (i32, i32) {
+        (self.col, self.row)
     }
 
     fn build(figure_type: FigureType, color: ChessColor, current_position: (i32, i32)) -> Figure {
@@ -74,44 +40,6 @@ impl Figure {
     }
 }
 
-#[derive(Clone, Copy, Eq, PartialEq,)]
-pub enum FigureType {
-    Pawn,
-    Fort,
-    Bishop,
-    Knight,
-    King,
-    Queen
-}
-
-impl FigureType {
-    fn get_str(&self) -> &str {
-        match self {
-            &Self::Pawn => {"PAWN"},
-            &Self::Fort => {"FORT"},
-            &Self::Bishop => {"BISHOP"},
-            &Self::Knight => {"KNIGHT"},
-            &Self::King => {"KING"},
-            &Self::Queen => {"QUEEN"},
-        }
-    }
-}
-
-#[derive(Clone, Copy, Eq, PartialEq,)]
-pub enum ChessColor {
-    White,
-    Black,
-}
-
-impl ChessColor {
-    fn get_str(&self) -> &str {
-        match self {
-            &Self::White => {"WHITE"},
-            &Self::Black => {"BLACK"},
-        }
-    }
-}
-
 #[derive(Component, Clone, Copy, PartialEq, Eq)]
 pub struct ChessTile {
     pub color: ChessColor,
@@ -135,293 +63,85 @@ impl ChessTile {
             row
         }
     }
+
+    pub fn square(&self) -> (i32, i32) {
+        (self.col, self.row)
+    }
 }
 
 #[derive(Component)]
 pub struct FigureMetadata {
-    number_of_moves: u16,
-    is_enpassant_valid: bool,
+    pub number_of_moves: u16,
+    pub is_enpassant_valid: bool,
 }
 
-pub fn get_figures(
-    commands: &mut Commands,
-    current_skins: &CurrentSkinSet,
-) {
-    //BLACK PAWNS
-    for col in 0..crate::game::GRID {
-        let row = 6;
-        let b_pawn = Figure::build_b_pawn((col, row));
-        commands.spawn_bundle(SpriteBundle{
-            transform: Transform {
-                translation: crate::game::transform_grid_to_world(col, row, crate::game::TILE_DIM, 0.0),
-                ..Default::default()
-            },
-            texture: current_skins.figures[&b_pawn.get_name()].clone(),
-            ..Default::default()
-        })
-        .insert(b_pawn)
-        .insert(FigureMetadata {
-            number_of_moves: 0,
-            is_enpassant_valid: false,
-        });
-    }
-
-    let b_fort = Figure::build_b_fort((0, 7));
-    commands.spawn_bundle(SpriteBundle{
-        transform: Transform {
-            translation: crate::game::transform_grid_to_world(0, 7, crate::game::TILE_DIM, 0.0),
-            ..Default::default()
-        },
-        texture: current_skins.figures[&b_fort.get_name()].clone(),
-        ..Default::default()
-    })
-    .insert(b_fort)
-    .insert(FigureMetadata {
-        number_of_moves: 0,
-        is_enpassant_valid: false,
-    });
-
-    let b_fort = Figure::build_b_fort((7, 7));
-    commands.spawn_bundle(SpriteBundle{
-        transform: Transform {
-            translation: crate::game::transform_grid_to_world(7, 7, crate::game::TILE_DIM, 0.0),
-            ..Default::default()
-        },
-        texture: current_skins.figures[&b_fort.get_name()].clone(),
-        ..Default::default()
-    })
-    .insert(b_fort)
-    .insert(FigureMetadata {
-        number_of_moves: 0,
-        is_enpassant_valid: false,
-    });
-
-    let b_knight = Figure::build_b_knight((1, 7));
-    commands.spawn_bundle(SpriteBundle{
-        transform: Transform {
-            translation: crate::game::transform_grid_to_world(1, 7, crate::game::TILE_DIM, 0.0),
-            ..Default::default()
-        },
-        texture: current_skins.figures[&b_knight.get_name()].clone(),
-        ..Default::default()
-    })
-    .insert(b_knight)
-    .insert(FigureMetadata {
-        number_of_moves: 0,
-        is_enpassant_valid: false,
-    });
-
-    let b_knight = Figure::build_b_knight((6, 7));
-    commands.spawn_bundle(SpriteBundle{
-        transform: Transform {
-            translation: crate::game::transform_grid_to_world(6, 7, crate::game::TILE_DIM, 0.0),
-            ..Default::default()
-        },
-        texture: current_skins.figures[&b_knight.get_name()].clone(),
-        ..Default::default()
-    })
-    .insert(b_knight)
-    .insert(FigureMetadata {
-        number_of_moves: 0,
-        is_enpassant_valid: false,
-    });
-
-    let b_bishop = Figure::build_b_bishop((2, 7));
-    commands.spawn_bundle(SpriteBundle{
-        transform: Transform {
-            translation: crate::game::transform_grid_to_world(2, 7, crate::game::TILE_DIM, 0.0),
-            ..Default::default()
-        },
-        texture: current_skins.figures[&b_bishop.get_name()].clone(),
-        ..Default::default()
-    })
-    .insert(b_bishop)
-    .insert(FigureMetadata {
-        number_of_moves: 0,
-        is_enpassant_valid: false,
-    });
 
-    let b_bishop = Figure::build_b_bishop((5, 7));
-    commands.spawn_bundle(SpriteBundle{
-        transform: Transform {
-            translation: crate::game::transform_grid_to_world(5, 7, crate::game::TILE_DIM, 0.0),
-            ..Default::default()
-        },
-        texture: current_skins.figures[&b_bishop.get_name()].clone(),
-        ..Default::default()
-    })
-    .insert(b_bishop)
-    .insert(FigureMetadata {
-        number_of_moves: 0,
-        is_enpassant_valid: false,
-    });
-
-    let b_queen = Figure::build_b_queen((3, 7));
-    commands.spawn_bundle(SpriteBundle{
-        transform: Transform {
-            translation: crate::game::transform_grid_to_world(3, 7, crate::game::TILE_DIM, 0.0),
-            ..Default::default()
-        },
-        texture: current_skins.figures[&b_queen.get_name()].clone(),
-        ..Default::default()
-    })
-    .insert(b_queen)
-    .insert(FigureMetadata {
-        number_of_moves: 0,
-        is_enpassant_valid: false,
-    });
+pub fn position_from_figures<'a>(
+    figures: impl Iterator<Item = (&'a Figure, &'a FigureMetadata)>,
+    side_to_move: ChessColor,
+) -> Position {
+    let mut position = Position::empty();
+    let mut unmoved = Vec::new();
+    for (fig, metadata) in figures {
+        position.set_piece(fig.square(), Some(fig.piece()));
+        if metadata.number_of_moves == 0 {
+            unmoved.push(fig.square());
+        }
+        if metadata.is_enpassant_valid {
+            let skipped_row = match fig.color {
+                ChessColor::White => fig.row - 1,
+                ChessColor::Black => fig.row + 1,
+            };
+            position.en_passant = Some((fig.col, skipped_row));
+        }
+    }
+    // castling needs both the king and the rook to have never moved
+    position.castling = CastlingRights {
+        white_king_side: unmoved.contains(&(4, 0)) && unmoved.contains(&(7, 0)),
+        white_queen_side: unmoved.contains(&(4, 0)) && unmoved.contains(&(0, 0)),
+        black_king_side: unmoved.contains(&(4, 7)) && unmoved.contains(&(7, 7)),
+        black_queen_side: unmoved.contains(&(4, 7)) && unmoved.contains(&(0, 7)),
+    };
+    position.side_to_move = side_to_move;
+    position.refresh_hash();
+    position
+}
 
-    let b_king = Figure::build_b_king((4, 7));
-    commands.spawn_bundle(SpriteBundle{
-        transform: Transform {
-            translation: crate::game::transform_grid_to_world(4, 7, crate::game::TILE_DIM, 0.0),
-            ..Default::default()
-        },
-        texture: current_skins.figures[&b_king.get_name()].clone(),
-        ..Default::default()
+// the pawn that made the double step en passant could take right now
+pub fn en_passant_pawn(position: &Position) -> Option<(i32, i32)> {
+    position.en_passant.map(|(col, row)| {
+        if row == 2 { (col, 3) } else { (col, 4) }
     })
-    .insert(b_king)
-    .insert(FigureMetadata {
-        number_of_moves: 0,
-        is_enpassant_valid: false,
-    });
+}
 
-    //WHITE PAWNS
-    for col in 0..crate::game::GRID {
-        let row = 1;
-        let w_pawn = Figure::build_w_pawn((col, row));
-        commands.spawn_bundle(SpriteBundle{
-            transform: Transform {
-                translation: crate::game::transform_grid_to_world(col, row, crate::game::TILE_DIM, 0.0),
-                ..Default::default()
-            },
-            texture: current_skins.figures[&w_pawn.get_name()].clone(),
-            ..Default::default()
-        })
-        .insert(w_pawn)
-        .insert(FigureMetadata {
+pub fn get_figures(
+    commands: &mut Commands,
+    current_skins: &CurrentSkinSet,
+    position: &Position,
+) {
+    for (square, piece) in position.pieces() {
+        spawn_figure(commands, current_skins, Figure::from_piece(piece, square), FigureMetadata {
             number_of_moves: 0,
-            is_enpassant_valid: false,
+            // a FEN can start right after a double step
+            is_enpassant_valid: Some(square) == en_passant_pawn(position),
         });
     }
+}
 
-    let w_fort = Figure::build_w_fort((0, 0));
-    commands.spawn_bundle(SpriteBundle{
-        transform: Transform {
-            translation: crate::game::transform_grid_to_world(0, 0, crate::game::TILE_DIM, 0.0),
-            ..Default::default()
-        },
-        texture: current_skins.figures[&w_fort.get_name()].clone(),
-        ..Default::default()
-    })
-    .insert(w_fort)
-    .insert(FigureMetadata {
-        number_of_moves: 0,
-        is_enpassant_valid: false,
-    });
-
-    let w_fort = Figure::build_w_fort((7, 0));
-    commands.spawn_bundle(SpriteBundle{
-        transform: Transform {
-            translation: crate::game::transform_grid_to_world(7, 0, crate::game::TILE_DIM, 0.0),
-            ..Default::default()
-        },
-        texture: current_skins.figures[&w_fort.get_name()].clone(),
-        ..Default::default()
-    })
-    .insert(w_fort)
-    .insert(FigureMetadata {
-        number_of_moves: 0,
-        is_enpassant_valid: false,
-    });
-
-    let w_knight = Figure::build_w_knight((1, 0));
-    commands.spawn_bundle(SpriteBundle{
-        transform: Transform {
-            translation: crate::game::transform_grid_to_world(1, 0, crate::game::TILE_DIM, 0.0),
-            ..Default::default()
-        },
-        texture: current_skins.figures[&w_knight.get_name()].clone(),
-        ..Default::default()
-    })
-    .insert(w_knight)
-    .insert(FigureMetadata {
-        number_of_moves: 0,
-        is_enpassant_valid: false,
-    });
-
-    let w_knight = Figure::build_w_knight((6, 0));
-    commands.spawn_bundle(SpriteBundle{
-        transform: Transform {
-            translation: crate::game::transform_grid_to_world(6, 0, crate::game::TILE_DIM, 0.0),
-            ..Default::default()
-        },
-        texture: current_skins.figures[&w_knight.get_name()].clone(),
-        ..Default::default()
-    })
-    .insert(w_knight)
-    .insert(FigureMetadata {
-        number_of_moves: 0,
-        is_enpassant_valid: false,
-    });
-
-    let w_bishop = Figure::build_w_bishop((2, 0));
-    commands.spawn_bundle(SpriteBundle{
-        transform: Transform {
-            translation: crate::game::transform_grid_to_world(2, 0, crate::game::TILE_DIM, 0.0),
-            ..Default::default()
-        },
-        texture: current_skins.figures[&w_bishop.get_name()].clone(),
-        ..Default::default()
-    })
-    .insert(w_bishop)
-    .insert(FigureMetadata {
-        number_of_moves: 0,
-        is_enpassant_valid: false,
-    });
-
-    let w_bishop = Figure::build_w_bishop((5, 0));
-    commands.spawn_bundle(SpriteBundle{
-        transform: Transform {
-            translation: crate::game::transform_grid_to_world(5, 0, crate::game::TILE_DIM, 0.0),
-            ..Default::default()
-        },
-        texture: current_skins.figures[&w_bishop.get_name()].clone(),
-        ..Default::default()
-    })
-    .insert(w_bishop)
-    .insert(FigureMetadata {
-        number_of_moves: 0,
-        is_enpassant_valid: false,
-    });
-
-    let w_queen = Figure::build_w_queen((3, 0));
-    commands.spawn_bundle(SpriteBundle{
-        transform: Transform {
-            translation: crate::game::transform_grid_to_world(3, 0, crate::game::TILE_DIM, 0.0),
-            ..Default::default()
-        },
-        texture: current_skins.figures[&w_queen.get_name()].clone(),
-        ..Default::default()
-    })
-    .insert(w_queen)
-    .insert(FigureMetadata {
-        number_of_moves: 0,
-        is_enpassant_valid: false,
-    });
-
-    let w_king = Figure::build_w_king((4, 0));
+pub fn spawn_figure(
+    commands: &mut Commands,
+    current_skins: &CurrentSkinSet,
+    figure: Figure,
+    metadata: FigureMetadata,
+) {
     commands.spawn_bundle(SpriteBundle{
         transform: Transform {
-            translation: crate::game::transform_grid_to_world(4, 0, crate::game::TILE_DIM, 0.0),
+            translation: crate::game::transform_grid_to_world(figure.col, figure.row, crate::game::TILE_DIM, 0.0),
             ..Default::default()
         },
-        texture: current_skins.figures[&w_king.get_name()].clone(),
+        texture: current_skins.figures[&figure.get_name()].clone(),
         ..Default::default()
     })
-    .insert(w_king)
-    .insert(FigureMetadata {
-        number_of_moves: 0,
-        is_enpassant_valid: false,
-    });
-}
\ No newline at end of file
+    .insert(figure)
+    .insert(metadata);
+}
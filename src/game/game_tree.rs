@@ -0,0 +1,241 @@
+use crate::engine::{Position, Move, ChessColor, notation};
+use super::history::HistoryEntry;
+
+const ROOT: usize = 0;
+
+// One position of the game tree. The first child continues the main line, any others are
+// side variations branching off here.
+struct Node {
+    // None only for the root, the starting position
+    entry: Option<HistoryEntry>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    comment: Option<String>,
+    // numeric annotation glyphs, $1 is a good move
+    nags: Vec<u8>,
+    // the child redo goes to, the one last left with undo
+    redo_child: Option<usize>,
+}
+
+impl Node {
+    fn build(entry: Option<HistoryEntry>, parent: Option<usize>) -> Node {
+        Node {
+            entry,
+            parent,
+            children: Vec::new(),
+            comment: None,
+            nags: Vec::new(),
+            redo_child: None,
+        }
+    }
+}
+
+// Every move ever played in the game, nothing is thrown away by undo or by playing a
+// different move from an earlier position. Deleted variations are only cut off from their
+// parent, their nodes stay in the arena.
+pub struct GameTree {
+    pub start: Position,
+    nodes: Vec<Node>,
+    current: usize,
+}
+
+impl GameTree {
+    pub fn new(start: Position) -> GameTree {
+        GameTree {
+            start,
+            nodes: vec![Node::build(None, None)],
+            current: ROOT,
+        }
+    }
+
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    // the moves from the start to `id`
+    pub fn path(&self, id: usize) -> Vec<HistoryEntry> {
+        let mut path = Vec::new();
+        let mut node = &self.nodes[id];
+        while let (Some(entry), Some(parent)) = (node.entry, node.parent) {
+            path.push(entry);
+            node = &self.nodes[parent];
+        }
+        path.reverse();
+        path
+    }
+
+    // node `ply` moves into the line leading to the current node
+    pub fn ancestor_at(&self, ply: usize) -> usize {
+        let mut id = self.current;
+        for _ in ply..self.path(self.current).len() {
+            id = self.nodes[id].parent.unwrap_or(ROOT);
+        }
+        id
+    }
+
+    // an already known move is followed, a new one becomes a variation next to the others
+    pub fn add_move(&mut self, entry: HistoryEntry) {
+        let existing = self.nodes[self.current].children.iter()
+            .copied()
+            .find(|child| self.nodes[*child].entry.map(|known| known.chess_move) == Some(entry.chess_move));
+        let child = match existing {
+            Some(child) => child,
+            None => {
+                self.nodes.push(Node::build(Some(entry), Some(self.current)));
+                let child = self.nodes.len() - 1;
+                self.nodes[self.current].children.push(child);
+                child
+            }
+        };
+        self.nodes[self.current].redo_child = Some(child);
+        self.current = child;
+    }
+
+    // steps back to the parent, the move stays in the tree for redo
+    pub fn back(&mut self) -> Option<HistoryEntry> {
+        let node = &self.nodes[self.current];
+        let (entry, parent) = (node.entry?, node.parent?);
+        self.nodes[parent].redo_child = Some(self.current);
+        self.current = parent;
+        Some(entry)
+    }
+
+    pub fn next_redo(&self) -> Option<Move> {
+        let node = &self.nodes[self.current];
+        node.redo_child
+            .or_else(|| node.children.first().copied())
+            .and_then(|child| self.nodes[child].entry)
+            .map(|entry| entry.chess_move)
+    }
+
+    pub fn jump_to(&mut self, id: usize) {
+        self.current = id;
+    }
+
+    // back to the starting position with redo following the main line again
+    pub fn rewind(&mut self) {
+        for node in self.nodes.iter_mut() {
+            node.redo_child = None;
+        }
+        self.current = ROOT;
+    }
+
+    // comment and glyphs of the current move, or of the start for the root
+    pub fn annotate(&mut self, comment: Option<String>, nags: Vec<u8>) {
+        let node = &mut self.nodes[self.current];
+        node.comment = comment;
+        node.nags = nags;
+    }
+
+    pub fn current_comment(&self) -> Option<&str> {
+        self.nodes[self.current].comment.as_deref()
+    }
+
+    // Makes the side line the current node is in the main line at its branching point.
+    // False if the current node is already on the main line.
+    pub fn promote_variation(&mut self) -> bool {
+        match self.branch_of_current() {
+            Some((parent, branch)) => {
+                let children = &mut self.nodes[parent].children;
+                children.retain(|child| *child != branch);
+                children.insert(0, branch);
+                true
+            },
+            None => false,
+        }
+    }
+
+    // Cuts off the side line the current node is in and goes back to where it branched off.
+    pub fn delete_variation(&mut self) -> bool {
+        match self.branch_of_current() {
+            Some((parent, branch)) => {
+                self.nodes[parent].children.retain(|child| *child != branch);
+                if self.nodes[parent].redo_child == Some(branch) {
+                    self.nodes[parent].redo_child = None;
+                }
+                self.current = parent;
+                true
+            },
+            None => false,
+        }
+    }
+
+    // the first node of the nearest side line holding the current node, with its parent
+    fn branch_of_current(&self) -> Option<(usize, usize)> {
+        let mut id = self.current;
+        while let Some(parent) = self.nodes[id].parent {
+            if self.nodes[parent].children.first() != Some(&id) {
+                return Some((parent, id));
+            }
+            id = parent;
+        }
+        None
+    }
+
+    // PGN movetext of the whole tree, side variations in nested parentheses
+    pub fn movetext(&self, result: &str) -> String {
+        self.write_movetext(result, &[])
+    }
+
+    // The same with the line leading to the current node as the main line, so a game that
+    // ended in a side line is written the way it was actually played.
+    pub fn played_movetext(&self, result: &str) -> String {
+        let mut played = vec![self.current];
+        let mut node = &self.nodes[self.current];
+        while let Some(parent) = node.parent {
+            played.push(parent);
+            node = &self.nodes[parent];
+        }
+        self.write_movetext(result, &played)
+    }
+
+    fn write_movetext(&self, result: &str, played: &[usize]) -> String {
+        let mut tokens = Vec::new();
+        if let Some(comment) = &self.nodes[ROOT].comment {
+            tokens.push(format!("{{{}}}", comment));
+        }
+        self.write_children(ROOT, &self.start, false, played, &mut tokens);
+        tokens.push(result.to_string());
+        tokens.join(" ")
+    }
+
+    // `played` children go first, otherwise the first child continues the line
+    fn write_children(&self, parent: usize, position: &Position, force_number: bool, played: &[usize], tokens: &mut Vec<String>) {
+        let children = &self.nodes[parent].children;
+        let main = match children.iter().find(|child| played.contains(child)).or_else(|| children.first()) {
+            Some(main) => *main,
+            None => return,
+        };
+        let after_main = self.write_move(main, position, force_number, tokens);
+
+        for variation in children.iter().filter(|child| **child != main) {
+            let mut variation_tokens = Vec::new();
+            let after_variation = self.write_move(*variation, position, true, &mut variation_tokens);
+            self.write_children(*variation, &after_variation, false, played, &mut variation_tokens);
+            tokens.push(format!("({})", variation_tokens.join(" ")));
+        }
+
+        // black's move needs its number again after anything got in between
+        let interrupted = children.len() > 1 || self.nodes[main].comment.is_some();
+        self.write_children(main, &after_main, interrupted, played, tokens);
+    }
+
+    fn write_move(&self, id: usize, position: &Position, force_number: bool, tokens: &mut Vec<String>) -> Position {
+        let node = &self.nodes[id];
+        let chess_move = node.entry.expect("Only the root has no move").chess_move;
+        match position.side_to_move {
+            ChessColor::White => tokens.push(format!("{}.", position.fullmove_number)),
+            ChessColor::Black if force_number => tokens.push(format!("{}...", position.fullmove_number)),
+            ChessColor::Black => {},
+        }
+        tokens.push(notation::to_san(position, chess_move));
+        tokens.extend(node.nags.iter().map(|nag| format!("${}", nag)));
+        if let Some(comment) = &node.comment {
+            tokens.push(format!("{{{}}}", comment));
+        }
+
+        let mut after_move = position.clone();
+        after_move.make_move(chess_move);
+        after_move
+    }
+}
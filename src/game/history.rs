@@ -0,0 +1,355 @@
+use std::marker::PhantomData;
+
+use bevy::prelude::*;
+use bevy::ecs::system::SystemParam;
+use super::figures::{self, Figure, FigureMetadata, FigureType};
+use super::clicker::TheTwoSelections;
+use super::promotion::PendingPromotion;
+use super::history_view::HistoryView;
+use super::move_entry::MoveEntry;
+use super::{ChessGame, CurrentSkinSet, LegalMoveEvent, TILE_DIM, transform_grid_to_world};
+use crate::app_states::AppState;
+use crate::engine::{Move, Undo, GRID};
+use crate::logger;
+use crate::main_menu::button_builder::{self, ButtonBuilder, ButtonType, ClassicButton};
+
+pub struct HistoryPlugin;
+
+impl Plugin for HistoryPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<UndoEvent>()
+            .add_event::<RedoEvent>()
+            .add_event::<PromoteVariationEvent>()
+            .add_event::<DeleteVariationEvent>()
+            .add_event::<SyncFiguresEvent>()
+            .add_system_set(
+                SystemSet::on_enter(AppState::InGame)
+                    .with_system(setup_history_buttons)
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(handle_history_buttons)
+                    .with_system(history_shortcuts)
+                    .with_system(undo_move)
+                    .with_system(redo_move)
+                    .with_system(edit_variations)
+                    .with_system(sync_figures)
+                    .with_system(log_movetext)
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::InGame)
+                    .with_system(close_history_buttons)
+            );
+    }
+}
+
+// one played move and everything needed to take it back
+#[derive(Clone, Copy)]
+pub struct HistoryEntry {
+    pub chess_move: Move,
+    pub undo: Undo,
+    pub metadata: MovedMetadata,
+}
+
+// FigureMetadata::number_of_moves of every figure the move touched, from before the move
+#[derive(Clone, Copy, Default)]
+pub struct MovedMetadata {
+    pub mover: u16,
+    pub rook: u16,
+    pub captured: u16,
+}
+
+pub struct UndoEvent;
+
+pub struct RedoEvent;
+
+pub struct PromoteVariationEvent;
+
+pub struct DeleteVariationEvent;
+
+// the live position jumped somewhere else, figure entities get set up from it again
+pub struct SyncFiguresEvent;
+
+#[derive(Component)]
+struct HistoryButtons;
+
+#[derive(Component)]
+struct HistoryButton {
+    button_type: HistoryButtonType,
+}
+
+impl ClassicButton for HistoryButton {
+    fn get_button_name(&self) -> String {
+        String::from("HISTORY BUTTON")
+    }
+
+    fn get_button_type(&self) -> Box<dyn ButtonType> {
+        Box::new(self.button_type)
+    }
+}
+
+#[derive(Clone, Copy)]
+enum HistoryButtonType {
+    Undo,
+    Redo,
+    Promote,
+    Delete,
+}
+
+impl ButtonType for HistoryButtonType {
+    fn get_type_name(&self) -> String {
+        match self {
+            HistoryButtonType::Undo => {String::from("UNDO")},
+            HistoryButtonType::Redo => {String::from("REDO")},
+            HistoryButtonType::Promote => {String::from("PROMOTE")},
+            HistoryButtonType::Delete => {String::from("DELETE")},
+        }
+    }
+}
+
+fn setup_history_buttons(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    commands.spawn_bundle(NodeBundle {
+        style: Style {
+            size: Size::new(Val::Px(270.0), Val::Px(360.0)),
+            position_type: PositionType::Absolute,
+            position: Rect {
+                right: Val::Px(10.0),
+                bottom: Val::Px(10.0),
+                ..Default::default()
+            },
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            flex_direction: FlexDirection::ColumnReverse,
+            ..Default::default()
+        },
+        color: Color::NONE.into(),
+        ..Default::default()
+    })
+    .with_children(|parent| {
+        let button_types = [
+            HistoryButtonType::Undo,
+            HistoryButtonType::Redo,
+            HistoryButtonType::Promote,
+            HistoryButtonType::Delete,
+        ];
+        for button_type in button_types {
+            ButtonBuilder::build_button(
+                parent,
+                HistoryButton {
+                    button_type,
+                },
+                &asset_server,
+            );
+        }
+    })
+    .insert(HistoryButtons);
+}
+
+type HistoryButtonQuery<'w, 's> = Query<
+    'w, 's,
+    (&'static Interaction, &'static mut UiColor, &'static HistoryButton),
+    (Changed<Interaction>, With<Button>)>;
+
+fn handle_history_buttons(
+    mut interaction_query: HistoryButtonQuery,
+    mut undo_event: EventWriter<UndoEvent>,
+    mut redo_event: EventWriter<RedoEvent>,
+    mut promote_event: EventWriter<PromoteVariationEvent>,
+    mut delete_event: EventWriter<DeleteVariationEvent>,
+) {
+    for (interaction, mut color, button) in interaction_query.iter_mut() {
+        match interaction {
+            Interaction::Clicked => {
+                *color = UiColor(button_builder::PRESSED_BUTTON);
+                match button.button_type {
+                    HistoryButtonType::Undo => undo_event.send(UndoEvent),
+                    HistoryButtonType::Redo => redo_event.send(RedoEvent),
+                    HistoryButtonType::Promote => promote_event.send(PromoteVariationEvent),
+                    HistoryButtonType::Delete => delete_event.send(DeleteVariationEvent),
+                }
+            },
+            Interaction::Hovered => {
+                *color = UiColor(button_builder::HOVERED_BUTTON);
+            },
+            Interaction::None => {
+                *color = UiColor(button_builder::NORMAL_BUTTON);
+            },
+        }
+    }
+}
+
+// Ctrl+Z and Ctrl+Y
+fn history_shortcuts(
+    keys: Res<Input<KeyCode>>,
+    mut undo_event: EventWriter<UndoEvent>,
+    mut redo_event: EventWriter<RedoEvent>,
+) {
+    if !keys.pressed(KeyCode::LControl) && !keys.pressed(KeyCode::RControl) {
+        return;
+    }
+    if keys.just_pressed(KeyCode::Z) {
+        undo_event.send(UndoEvent);
+    }
+    if keys.just_pressed(KeyCode::Y) {
+        redo_event.send(RedoEvent);
+    }
+}
+
+// what undo_move only looks at
+#[derive(SystemParam)]
+struct UndoResources<'w, 's> {
+    pending_promotion: Res<'w, PendingPromotion>,
+    history_view: Res<'w, HistoryView>,
+    current_skins: Res<'w, CurrentSkinSet>,
+    #[system_param(ignore)]
+    marker: PhantomData<&'s ()>,
+}
+
+fn undo_move(
+    mut commands: Commands,
+    mut undo_event: EventReader<UndoEvent>,
+    mut game: ResMut<ChessGame>,
+    mut selection: ResMut<TheTwoSelections>,
+    resources: UndoResources,
+    mut figures: Query<(&mut Transform, &mut Handle<Image>, &mut Figure, &mut FigureMetadata)>,
+) {
+    for _ in undo_event.iter() {
+        if resources.pending_promotion.legal_move.is_some() || resources.history_view.is_viewing() {
+            continue;
+        }
+        let entry = match game.undo() {
+            Some(entry) => entry,
+            None => continue,
+        };
+        let chess_move = entry.chess_move;
+        logger::log(format!("Undo {}", chess_move));
+
+        let en_passant_pawn = game.en_passant_pawn();
+        let castling_rook = chess_move.castling_rook_move();
+        for (mut transform, mut texture, mut fig, mut metadata) in figures.iter_mut() {
+            let square = fig.square();
+            if square == chess_move.to {
+                transform.translation = transform_grid_to_world(chess_move.from.0, chess_move.from.1, TILE_DIM, 0.0);
+                fig.col = chess_move.from.0;
+                fig.row = chess_move.from.1;
+                metadata.number_of_moves = entry.metadata.mover;
+                if fig.figure_type != entry.undo.moved.figure_type {
+                    fig.figure_type = FigureType::Pawn;
+                    *texture = resources.current_skins.figures[&fig.get_name()].clone();
+                }
+            } else if let Some((rook_from, _)) = castling_rook.filter(|(_, rook_to)| *rook_to == square) {
+                transform.translation = transform_grid_to_world(rook_from.0, rook_from.1, TILE_DIM, 0.0);
+                fig.col = rook_from.0;
+                fig.row = rook_from.1;
+                metadata.number_of_moves = entry.metadata.rook;
+            }
+            metadata.is_enpassant_valid = Some(fig.square()) == en_passant_pawn;
+        }
+        if let (Some(square), Some(captured)) = (chess_move.captured_square(), entry.undo.captured) {
+            figures::spawn_figure(&mut commands, &resources.current_skins, Figure::from_piece(captured, square), FigureMetadata {
+                number_of_moves: entry.metadata.captured,
+                is_enpassant_valid: Some(square) == en_passant_pawn,
+            });
+        }
+
+        selection.selection1 = None;
+        selection.selection2 = None;
+    }
+}
+
+// the undone move is simply played again, the game tree follows it instead of adding it twice
+fn redo_move(
+    mut redo_event: EventReader<RedoEvent>,
+    game: Res<ChessGame>,
+    pending_promotion: Res<PendingPromotion>,
+    history_view: Res<HistoryView>,
+    mut legalmove_event: EventWriter<LegalMoveEvent>,
+) {
+    if redo_event.iter().count() == 0 || pending_promotion.legal_move.is_some() || history_view.is_viewing() {
+        return;
+    }
+    if let Some(chess_move) = game.next_redo() {
+        logger::log(format!("Redo {}", chess_move));
+        legalmove_event.send(LegalMoveEvent::build(&game.position, chess_move));
+    }
+}
+
+// both work on the side line the live position is in
+fn edit_variations(
+    mut promote_event: EventReader<PromoteVariationEvent>,
+    mut delete_event: EventReader<DeleteVariationEvent>,
+    mut game: ResMut<ChessGame>,
+    pending_promotion: Res<PendingPromotion>,
+    history_view: Res<HistoryView>,
+    mut sync_event: EventWriter<SyncFiguresEvent>,
+) {
+    let (promote, delete) = (promote_event.iter().count() > 0, delete_event.iter().count() > 0);
+    if pending_promotion.legal_move.is_some() || history_view.is_viewing() {
+        return;
+    }
+    if promote {
+        if game.promote_variation() {
+            logger::log("Variation promoted to main line");
+        } else {
+            logger::log("Already on the main line");
+        }
+    }
+    if delete {
+        if game.delete_variation() {
+            logger::log("Variation deleted");
+            sync_event.send(SyncFiguresEvent);
+        } else {
+            logger::log("The main line can not be deleted");
+        }
+    }
+}
+
+fn sync_figures(
+    mut commands: Commands,
+    mut sync_event: EventReader<SyncFiguresEvent>,
+    game: Res<ChessGame>,
+    current_skins: Res<CurrentSkinSet>,
+    mut selection: ResMut<TheTwoSelections>,
+    figures: Query<Entity, With<Figure>>,
+) {
+    if sync_event.iter().count() == 0 {
+        return;
+    }
+    for entity in figures.iter() {
+        commands.entity(entity).despawn();
+    }
+    let number_of_moves = game.number_of_moves();
+    for (square, piece) in game.position.pieces() {
+        figures::spawn_figure(&mut commands, &current_skins, Figure::from_piece(piece, square), FigureMetadata {
+            number_of_moves: number_of_moves[(square.1 * GRID + square.0) as usize],
+            is_enpassant_valid: Some(square) == game.en_passant_pawn(),
+        });
+    }
+
+    selection.selection1 = None;
+    selection.selection2 = None;
+}
+
+// P prints the whole game with its variations
+fn log_movetext(
+    keys: Res<Input<KeyCode>>,
+    game: Res<ChessGame>,
+    move_entry: Res<MoveEntry>,
+) {
+    if keys.just_pressed(KeyCode::P) && !move_entry.is_typing() {
+        logger::log(game.movetext());
+    }
+}
+
+fn close_history_buttons(
+    mut commands: Commands,
+    buttons: Query<Entity, With<HistoryButtons>>,
+) {
+    for entity in buttons.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
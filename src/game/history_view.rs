@@ -0,0 +1,163 @@
+use bevy::prelude::*;
+use super::figures::Figure;
+use super::promotion::PendingPromotion;
+use super::clicker::TheTwoSelections;
+use super::history::SyncFiguresEvent;
+use super::move_entry::MoveEntry;
+use super::{ChessGame, CurrentSkinSet, TILE_DIM, transform_grid_to_world};
+use crate::app_states::AppState;
+
+pub struct HistoryViewPlugin;
+
+impl Plugin for HistoryViewPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(HistoryView::default())
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(browse_history)
+                    .with_system(play_from_history)
+                    .with_system(show_history_view)
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::InGame)
+                    .with_system(close_history_view)
+            );
+    }
+}
+
+// which earlier position is on the board, None while looking at the live game
+#[derive(Default)]
+pub struct HistoryView {
+    pub ply: Option<usize>,
+}
+
+impl HistoryView {
+    pub fn is_viewing(&self) -> bool {
+        self.ply.is_some()
+    }
+}
+
+// stand-ins for the figures of the viewed position, the real ones are only hidden meanwhile
+#[derive(Component)]
+struct HistoryFigure;
+
+#[derive(Component)]
+struct HistoryIndicator;
+
+// left arrow goes a move back, right arrow a move forward and past the last one back to the game
+fn browse_history(
+    keys: Res<Input<KeyCode>>,
+    game: Res<ChessGame>,
+    pending_promotion: Res<PendingPromotion>,
+    move_entry: Res<MoveEntry>,
+    mut selection: ResMut<TheTwoSelections>,
+    mut history_view: ResMut<HistoryView>,
+) {
+    if pending_promotion.legal_move.is_some() || move_entry.is_typing() {
+        return;
+    }
+    let live = game.ply_count();
+    let current = history_view.ply.unwrap_or(live);
+    let ply = if keys.just_pressed(KeyCode::Left) {
+        current.saturating_sub(1)
+    } else if keys.just_pressed(KeyCode::Right) {
+        (current + 1).min(live)
+    } else {
+        return;
+    };
+    let ply = if ply == live { None } else { Some(ply) };
+    if ply != history_view.ply {
+        selection.selection1 = None;
+        selection.selection2 = None;
+        history_view.ply = ply;
+    }
+}
+
+// Enter makes the viewed position the live one, a different move from there then starts a
+// side variation and the line played so far stays in the game tree
+fn play_from_history(
+    keys: Res<Input<KeyCode>>,
+    mut game: ResMut<ChessGame>,
+    mut history_view: ResMut<HistoryView>,
+    mut sync_event: EventWriter<SyncFiguresEvent>,
+) {
+    if !keys.just_pressed(KeyCode::Return) {
+        return;
+    }
+    if let Some(ply) = history_view.ply.take() {
+        game.go_back_to(ply);
+        sync_event.send(SyncFiguresEvent);
+    }
+}
+
+// everything the history view puts on the board
+type HistoryShownQuery<'w, 's> = Query<'w, 's, Entity, Or<(With<HistoryFigure>, With<HistoryIndicator>)>>;
+
+fn show_history_view(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    history_view: Res<HistoryView>,
+    game: Res<ChessGame>,
+    current_skins: Res<CurrentSkinSet>,
+    mut live_figures: Query<&mut Visibility, With<Figure>>,
+    shown: HistoryShownQuery,
+) {
+    if !history_view.is_changed() {
+        return;
+    }
+    for entity in shown.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    for mut visibility in live_figures.iter_mut() {
+        visibility.is_visible = !history_view.is_viewing();
+    }
+    let ply = match history_view.ply {
+        Some(ply) => ply,
+        None => return,
+    };
+
+    let position = game.position_at(ply);
+    for (square, piece) in position.pieces() {
+        let figure = Figure::from_piece(piece, square);
+        commands.spawn_bundle(SpriteBundle {
+            transform: Transform {
+                translation: transform_grid_to_world(square.0, square.1, TILE_DIM, 0.0),
+                ..Default::default()
+            },
+            texture: current_skins.figures[&figure.get_name()].clone(),
+            ..Default::default()
+        })
+        .insert(HistoryFigure);
+    }
+
+    commands.spawn_bundle(TextBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: Rect {
+                left: Val::Px(10.0),
+                top: Val::Px(10.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        text: Text::with_section(
+            format!("VIEWING HISTORY {}/{} - ENTER TO PLAY FROM HERE", ply, game.ply_count()),
+            TextStyle { font: asset_server.load("./fonts/Symtext.ttf"), font_size: 30.0, color: Color::rgb(0.9, 0.9, 0.9) },
+            Default::default()
+        ),
+        ..Default::default()
+    })
+    .insert(HistoryIndicator);
+}
+
+fn close_history_view(
+    mut commands: Commands,
+    mut history_view: ResMut<HistoryView>,
+    shown: HistoryShownQuery,
+) {
+    history_view.ply = None;
+    for entity in shown.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
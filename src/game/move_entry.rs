@@ -0,0 +1,128 @@
+use bevy::prelude::*;
+use super::promotion::PendingPromotion;
+use super::history_view::HistoryView;
+use super::{ChessGame, LegalMoveEvent};
+use crate::app_states::AppState;
+use crate::engine::notation;
+use crate::logger;
+
+pub struct MoveEntryPlugin;
+
+impl Plugin for MoveEntryPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(MoveEntry::default())
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(type_move)
+                    .with_system(show_move_entry)
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::InGame)
+                    .with_system(close_move_entry)
+            );
+    }
+}
+
+// the move being typed, None while the keyboard is left to the shortcuts
+#[derive(Default)]
+pub struct MoveEntry {
+    pub text: Option<String>,
+}
+
+impl MoveEntry {
+    pub fn is_typing(&self) -> bool {
+        self.text.is_some()
+    }
+}
+
+#[derive(Component)]
+struct MoveEntryText;
+
+// Tab opens the entry, Enter plays what was typed as SAN (Nf3) or UCI (g1f3), Escape drops it
+fn type_move(
+    keys: Res<Input<KeyCode>>,
+    mut characters: EventReader<ReceivedCharacter>,
+    game: Res<ChessGame>,
+    pending_promotion: Res<PendingPromotion>,
+    history_view: Res<HistoryView>,
+    mut move_entry: ResMut<MoveEntry>,
+    mut legal_move_event: EventWriter<LegalMoveEvent>,
+) {
+    let text = match &mut move_entry.text {
+        Some(text) => text,
+        None => {
+            characters.iter().for_each(drop);
+            if keys.just_pressed(KeyCode::Tab) && pending_promotion.legal_move.is_none() && !history_view.is_viewing() {
+                move_entry.text = Some(String::new());
+            }
+            return;
+        },
+    };
+    for character in characters.iter() {
+        if character.char.is_ascii_alphanumeric() || matches!(character.char, '=' | '-' | '+' | '#') {
+            text.push(character.char);
+        }
+    }
+    if keys.just_pressed(KeyCode::Back) {
+        text.pop();
+    }
+    if keys.just_pressed(KeyCode::Escape) {
+        move_entry.text = None;
+    } else if keys.just_pressed(KeyCode::Return) {
+        let typed = move_entry.text.take().unwrap_or_default();
+        let played = notation::from_san(&game.position, &typed)
+            .or_else(|san_error| notation::from_uci(&game.position, &typed).map_err(|_| san_error));
+        match played {
+            Ok(chess_move) => legal_move_event.send(LegalMoveEvent::build(&game.position, chess_move)),
+            Err(error) => logger::log(error),
+        }
+    }
+}
+
+fn show_move_entry(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    move_entry: Res<MoveEntry>,
+    shown: Query<Entity, With<MoveEntryText>>,
+) {
+    if !move_entry.is_changed() {
+        return;
+    }
+    for entity in shown.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    let text = match &move_entry.text {
+        Some(text) => text,
+        None => return,
+    };
+    commands.spawn_bundle(TextBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: Rect {
+                left: Val::Px(10.0),
+                top: Val::Px(50.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        text: Text::with_section(
+            format!("MOVE: {}_", text),
+            TextStyle { font: asset_server.load("./fonts/Symtext.ttf"), font_size: 30.0, color: Color::rgb(0.9, 0.9, 0.9) },
+            Default::default()
+        ),
+        ..Default::default()
+    })
+    .insert(MoveEntryText);
+}
+
+fn close_move_entry(
+    mut commands: Commands,
+    mut move_entry: ResMut<MoveEntry>,
+    shown: Query<Entity, With<MoveEntryText>>,
+) {
+    move_entry.text = None;
+    for entity in shown.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
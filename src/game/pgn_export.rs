@@ -0,0 +1,198 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::*;
+use super::{ChessGame, GameResult};
+use crate::app_states::AppState;
+use crate::engine::{fen, pgn, Position, ChessColor};
+use crate::engine::pgn::PgnTags;
+use crate::logger;
+use crate::main_menu::button_builder::{self, ButtonBuilder, ButtonType, ClassicButton};
+
+pub struct PgnExportPlugin;
+
+impl Plugin for PgnExportPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_system_set(
+                SystemSet::on_enter(AppState::InGame)
+                    .with_system(setup_export_button)
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(handle_export_button)
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::InGame)
+                    .with_system(close_export_button)
+            );
+    }
+}
+
+// where exported games are written to, `--pgn-dir <directory>` on the command line
+pub struct PgnSettings {
+    pub directory: PathBuf,
+}
+
+impl Default for PgnSettings {
+    fn default() -> PgnSettings {
+        PgnSettings {
+            directory: PathBuf::from("./games"),
+        }
+    }
+}
+
+#[derive(Component)]
+struct ExportPanel;
+
+#[derive(Component)]
+struct ExportButton;
+
+impl ClassicButton for ExportButton {
+    fn get_button_name(&self) -> String {
+        String::from("EXPORT BUTTON")
+    }
+
+    fn get_button_type(&self) -> Box<dyn ButtonType> {
+        Box::new(ExportButtonType)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct ExportButtonType;
+
+impl ButtonType for ExportButtonType {
+    fn get_type_name(&self) -> String {
+        String::from("EXPORT PGN")
+    }
+}
+
+// Writes the game with all its variations into a new file, the line leading to the current position
+// as the main line. `result` is None while the game is still going on.
+pub fn export_pgn(game: &ChessGame, result: Option<&GameResult>, settings: &PgnSettings) {
+    let (since_epoch, date) = match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(since_epoch) => (since_epoch.as_secs(), pgn_date(since_epoch.as_secs())),
+        Err(_) => (0, String::from("????.??.??")),
+    };
+    let result_str = match result {
+        Some(result) => match result.winner {
+            Some(ChessColor::White) => "1-0",
+            Some(ChessColor::Black) => "0-1",
+            None => "1/2-1/2",
+        },
+        None => "*",
+    };
+    let tags = PgnTags {
+        event: String::from("Sahister game"),
+        date,
+        result: result_str.to_string(),
+        fen: if game.tree.start != Position::starting() { Some(fen::write(&game.tree.start)) } else { None },
+        termination: Some(String::from(if result.is_some() { "normal" } else { "unterminated" })),
+        ..Default::default()
+    };
+
+    let written = fs::create_dir_all(&settings.directory)
+        .and_then(|_| create_export_file(&settings.directory, since_epoch))
+        .and_then(|(path, mut file)| {
+            file.write_all(pgn::write(&tags, &game.tree.played_movetext(result_str)).as_bytes())?;
+            Ok(path)
+        });
+    match written {
+        Ok(path) => logger::log(format!("Game exported to {}", path.display())),
+        Err(error) => logger::log(format!("Could not export the game to {}: {}", settings.directory.display(), error)),
+    }
+}
+
+// a second export within the same second gets sahister-<secs>-2.pgn and so on, nothing is overwritten
+fn create_export_file(directory: &Path, since_epoch: u64) -> io::Result<(PathBuf, File)> {
+    let mut counter = 1;
+    loop {
+        let name = match counter {
+            1 => format!("sahister-{}.pgn", since_epoch),
+            _ => format!("sahister-{}-{}.pgn", since_epoch, counter),
+        };
+        let path = directory.join(name);
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(error) if error.kind() == io::ErrorKind::AlreadyExists => counter += 1,
+            Err(error) => return Err(error),
+        }
+    }
+}
+
+// YYYY.MM.DD out of the seconds since 1970, days to civil date as in Howard Hinnant's algorithm
+fn pgn_date(since_epoch: u64) -> String {
+    let days = (since_epoch / 86400) as i64 + 719468;
+    let era = days / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}.{:02}.{:02}", year, month, day)
+}
+
+fn setup_export_button(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    commands.spawn_bundle(NodeBundle {
+        style: Style {
+            size: Size::new(Val::Px(270.0), Val::Px(90.0)),
+            position_type: PositionType::Absolute,
+            position: Rect {
+                right: Val::Px(10.0),
+                top: Val::Px(10.0),
+                ..Default::default()
+            },
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..Default::default()
+        },
+        color: Color::NONE.into(),
+        ..Default::default()
+    })
+    .with_children(|parent| {
+        ButtonBuilder::build_button(parent, ExportButton, &asset_server);
+    })
+    .insert(ExportPanel);
+}
+
+type ExportButtonQuery<'w, 's> = Query<
+    'w, 's,
+    (&'static Interaction, &'static mut UiColor),
+    (Changed<Interaction>, With<Button>, With<ExportButton>)>;
+
+fn handle_export_button(
+    mut interaction_query: ExportButtonQuery,
+    game: Res<ChessGame>,
+    settings: Res<PgnSettings>,
+) {
+    for (interaction, mut color) in interaction_query.iter_mut() {
+        match interaction {
+            Interaction::Clicked => {
+                *color = UiColor(button_builder::PRESSED_BUTTON);
+                export_pgn(&game, None, &settings);
+            },
+            Interaction::Hovered => {
+                *color = UiColor(button_builder::HOVERED_BUTTON);
+            },
+            Interaction::None => {
+                *color = UiColor(button_builder::NORMAL_BUTTON);
+            },
+        }
+    }
+}
+
+fn close_export_button(
+    mut commands: Commands,
+    panels: Query<Entity, With<ExportPanel>>,
+) {
+    for panel in panels.iter() {
+        commands.entity(panel).despawn_recursive();
+    }
+}
@@ -0,0 +1,143 @@
+use bevy::prelude::*;
+use super::figures::FigureType;
+use super::LegalMoveEvent;
+use crate::app_states::AppState;
+use crate::main_menu::button_builder::{self, ButtonBuilder, ButtonType, ClassicButton};
+
+pub struct PromotionPlugin;
+
+impl Plugin for PromotionPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(PendingPromotion {
+                legal_move: None,
+            })
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(open_promotion_popup)
+                    .with_system(handle_promotion_buttons)
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::InGame)
+                    .with_system(close_promotion_popup)
+            );
+    }
+}
+
+// the move waits in here until a figure is picked
+pub struct PendingPromotion {
+    pub legal_move: Option<LegalMoveEvent>,
+}
+
+#[derive(Component)]
+struct PromotionPopup;
+
+#[derive(Component)]
+struct PromotionButton {
+    figure_type: FigureType,
+}
+
+impl ClassicButton for PromotionButton {
+    fn get_button_name(&self) -> String {
+        String::from("PROMOTION BUTTON")
+    }
+
+    fn get_button_type(&self) -> Box<dyn ButtonType> {
+        Box::new(self.figure_type)
+    }
+}
+
+impl ButtonType for FigureType {
+    fn get_type_name(&self) -> String {
+        self.get_str().to_string()
+    }
+}
+
+fn open_promotion_popup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    pending: Res<PendingPromotion>,
+) {
+    if !pending.is_changed() || pending.legal_move.is_none() {
+        return;
+    }
+
+    commands.spawn_bundle(NodeBundle {
+        style: Style {
+            size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+            position_type: PositionType::Absolute,
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..Default::default()
+        },
+        color: Color::NONE.into(),
+        ..Default::default()
+    })
+    .with_children(|parent| {
+        parent.spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Px(300.0), Val::Px(400.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::ColumnReverse,
+                ..Default::default()
+            },
+            image: UiImage(asset_server.load("./sprites/ui/background.png")),
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            for figure_type in [FigureType::Queen, FigureType::Fort, FigureType::Bishop, FigureType::Knight] {
+                ButtonBuilder::build_button(
+                    parent,
+                    PromotionButton {
+                        figure_type,
+                    },
+                    &asset_server,
+                );
+            }
+        });
+    })
+    .insert(PromotionPopup);
+}
+
+fn handle_promotion_buttons(
+    mut commands: Commands,
+    mut interaction_query: Query<
+        (&Interaction, &mut UiColor, &PromotionButton),
+        (Changed<Interaction>, With<Button>)>,
+    mut pending: ResMut<PendingPromotion>,
+    mut legalmove_event: EventWriter<LegalMoveEvent>,
+    popups: Query<Entity, With<PromotionPopup>>,
+) {
+    for (interaction, mut color, button) in interaction_query.iter_mut() {
+        match interaction {
+            Interaction::Clicked => {
+                *color = UiColor(button_builder::PRESSED_BUTTON);
+                if let Some(mut legal_move) = pending.legal_move.take() {
+                    legal_move.chess_move.promotion = Some(button.figure_type);
+                    legalmove_event.send(legal_move);
+                }
+                for popup in popups.iter() {
+                    commands.entity(popup).despawn_recursive();
+                }
+            },
+            Interaction::Hovered => {
+                *color = UiColor(button_builder::HOVERED_BUTTON);
+            },
+            Interaction::None => {
+                *color = UiColor(button_builder::NORMAL_BUTTON);
+            },
+        }
+    }
+}
+
+fn close_promotion_popup(
+    mut commands: Commands,
+    mut pending: ResMut<PendingPromotion>,
+    popups: Query<Entity, With<PromotionPopup>>,
+) {
+    pending.legal_move = None;
+    for popup in popups.iter() {
+        commands.entity(popup).despawn_recursive();
+    }
+}
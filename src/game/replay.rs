@@ -0,0 +1,228 @@
+use std::fs;
+use std::path::Path;
+
+use bevy::prelude::*;
+use super::history::{UndoEvent, RedoEvent};
+use super::{ChessGame, StartingPosition};
+use crate::app_states::AppState;
+use crate::engine::pgn::{self, PgnGame};
+use crate::logger;
+use crate::main_menu::button_builder::{self, ButtonBuilder, ButtonType, ClassicButton};
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_system_set(
+                SystemSet::on_enter(AppState::InGame)
+                    .with_system(setup_replay)
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(handle_replay_buttons)
+                    .with_system(replay_shortcuts)
+                    .with_system(show_comment)
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::InGame)
+                    .with_system(close_replay)
+            );
+    }
+}
+
+// Reads game `number` (from 1) out of a PGN file, `--pgn <file> --game <number>` on the command
+// line. Every move in it has to be legal, else nothing is replayed.
+pub fn load_game(path: &Path, number: usize) -> Option<PgnGame> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(error) => {
+            logger::log(format!("Could not read {}: {}", path.display(), error));
+            return None;
+        },
+    };
+    let mut games = match pgn::parse(&text) {
+        Ok(games) => games,
+        Err(error) => {
+            logger::log(error);
+            return None;
+        },
+    };
+    logger::log(format!("{} has {} games", path.display(), games.len()));
+    if number == 0 || number > games.len() {
+        logger::log(format!("There is no game {} in {}", number, path.display()));
+        return None;
+    }
+    let game = games.swap_remove(number - 1);
+    match game.validate() {
+        Ok(_) => Some(game),
+        Err(error) => {
+            logger::log(error);
+            None
+        },
+    }
+}
+
+#[derive(Component)]
+struct ReplayPanel;
+
+#[derive(Component)]
+struct ReplayComment;
+
+#[derive(Component)]
+struct ReplayButton {
+    button_type: ReplayButtonType,
+}
+
+impl ClassicButton for ReplayButton {
+    fn get_button_name(&self) -> String {
+        String::from("REPLAY BUTTON")
+    }
+
+    fn get_button_type(&self) -> Box<dyn ButtonType> {
+        Box::new(self.button_type)
+    }
+}
+
+#[derive(Clone, Copy)]
+enum ReplayButtonType {
+    StepBackButton,
+    StepForwardButton,
+}
+
+impl ButtonType for ReplayButtonType {
+    fn get_type_name(&self) -> String {
+        match self {
+            Self::StepBackButton => String::from("STEP BACK"),
+            Self::StepForwardButton => String::from("STEP FORWARD"),
+        }
+    }
+}
+
+// the players and the comment of the current move, with the two step buttons below
+fn setup_replay(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    starting_position: Res<StartingPosition>,
+) {
+    let pgn_game = match &starting_position.pgn {
+        Some(pgn_game) => pgn_game,
+        None => return,
+    };
+    let text_style = TextStyle {
+        font: asset_server.load("./fonts/Symtext.ttf"),
+        font_size: 24.0,
+        color: Color::rgb(0.9, 0.9, 0.9),
+    };
+    let players = format!(
+        "{} - {} {}",
+        pgn_game.tag("White").unwrap_or("?"),
+        pgn_game.tag("Black").unwrap_or("?"),
+        pgn_game.result,
+    );
+
+    commands.spawn_bundle(NodeBundle {
+        style: Style {
+            size: Size::new(Val::Px(270.0), Val::Px(300.0)),
+            position_type: PositionType::Absolute,
+            position: Rect {
+                left: Val::Px(10.0),
+                bottom: Val::Px(10.0),
+                ..Default::default()
+            },
+            justify_content: JustifyContent::FlexStart,
+            align_items: AlignItems::Center,
+            flex_direction: FlexDirection::ColumnReverse,
+            ..Default::default()
+        },
+        color: Color::NONE.into(),
+        ..Default::default()
+    })
+    .with_children(|parent| {
+        parent.spawn_bundle(TextBundle {
+            text: Text::with_section(players, text_style.clone(), Default::default()),
+            ..Default::default()
+        });
+        parent.spawn_bundle(TextBundle {
+            style: Style {
+                max_size: Size::new(Val::Px(270.0), Val::Undefined),
+                ..Default::default()
+            },
+            text: Text::with_section("", text_style, Default::default()),
+            ..Default::default()
+        })
+        .insert(ReplayComment);
+        for button_type in [ReplayButtonType::StepBackButton, ReplayButtonType::StepForwardButton] {
+            ButtonBuilder::build_button(parent, ReplayButton { button_type }, &asset_server);
+        }
+    })
+    .insert(ReplayPanel);
+}
+
+type ReplayButtonQuery<'w, 's> = Query<
+    'w, 's,
+    (&'static Interaction, &'static mut UiColor, &'static ReplayButton),
+    (Changed<Interaction>, With<Button>)>;
+
+fn handle_replay_buttons(
+    mut interaction_query: ReplayButtonQuery,
+    mut undo_event: EventWriter<UndoEvent>,
+    mut redo_event: EventWriter<RedoEvent>,
+) {
+    for (interaction, mut color, button) in interaction_query.iter_mut() {
+        match interaction {
+            Interaction::Clicked => {
+                *color = UiColor(button_builder::PRESSED_BUTTON);
+                match button.button_type {
+                    ReplayButtonType::StepBackButton => undo_event.send(UndoEvent),
+                    ReplayButtonType::StepForwardButton => redo_event.send(RedoEvent),
+                }
+            },
+            Interaction::Hovered => {
+                *color = UiColor(button_builder::HOVERED_BUTTON);
+            },
+            Interaction::None => {
+                *color = UiColor(button_builder::NORMAL_BUTTON);
+            },
+        }
+    }
+}
+
+// down arrow steps a move forward along the main line, up arrow a move back
+fn replay_shortcuts(
+    keys: Res<Input<KeyCode>>,
+    starting_position: Res<StartingPosition>,
+    mut undo_event: EventWriter<UndoEvent>,
+    mut redo_event: EventWriter<RedoEvent>,
+) {
+    if starting_position.pgn.is_none() {
+        return;
+    }
+    if keys.just_pressed(KeyCode::Down) {
+        redo_event.send(RedoEvent);
+    }
+    if keys.just_pressed(KeyCode::Up) {
+        undo_event.send(UndoEvent);
+    }
+}
+
+fn show_comment(
+    game: Res<ChessGame>,
+    mut comments: Query<&mut Text, With<ReplayComment>>,
+) {
+    if !game.is_changed() {
+        return;
+    }
+    for mut text in comments.iter_mut() {
+        text.sections[0].value = game.tree.current_comment().unwrap_or("").to_string();
+    }
+}
+
+fn close_replay(
+    mut commands: Commands,
+    panels: Query<Entity, With<ReplayPanel>>,
+) {
+    for panel in panels.iter() {
+        commands.entity(panel).despawn_recursive();
+    }
+}
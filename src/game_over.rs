@@ -0,0 +1,185 @@
+use bevy::prelude::*;
+use crate::{app_states::AppState, logger};
+use crate::game::{ChessGame, GameResult};
+use crate::game::pgn_export::{self, PgnSettings};
+use crate::main_menu::button_builder::{self, ButtonBuilder, ButtonType, ClassicButton};
+use crate::main_menu::button_events::MainMenuEvent;
+
+pub struct GameOverPlugin;
+
+impl Plugin for GameOverPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_system_set(
+                SystemSet::on_enter(AppState::GameOver)
+                .with_system(setup_game_over)
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::GameOver)
+                .with_system(handle_game_over_buttons)
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::GameOver)
+                .with_system(close_game_over)
+            );
+    }
+}
+
+#[derive(Component)]
+struct GameOverScreen;
+
+#[derive(Component)]
+struct GameOverButton {
+    button_type: GameOverButtonType,
+}
+
+impl ClassicButton for GameOverButton {
+    fn get_button_name(&self) -> String {
+        String::from("GAME OVER BUTTON")
+    }
+
+    fn get_button_type(&self) -> Box<dyn ButtonType> {
+        Box::new(self.button_type)
+    }
+}
+
+#[derive(Clone, Copy)]
+enum GameOverButtonType {
+    RematchButton,
+    ExportButton,
+    MainMenuButton,
+}
+
+impl ButtonType for GameOverButtonType {
+    fn get_type_name(&self) -> String {
+        match self {
+            GameOverButtonType::RematchButton => {String::from("REMATCH")},
+            GameOverButtonType::ExportButton => {String::from("EXPORT PGN")},
+            GameOverButtonType::MainMenuButton => {String::from("MAIN MENU")},
+        }
+    }
+}
+
+fn setup_game_over(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    result: Res<GameResult>,
+) {
+    let winner = match result.winner {
+        Some(color) => format!("{} WINS", color.get_str()),
+        None => String::from("DRAW"),
+    };
+
+    commands.spawn_bundle(NodeBundle {
+        style: Style {
+            size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+            position_type: PositionType::Absolute,
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..Default::default()
+        },
+        color: Color::NONE.into(),
+        ..Default::default()
+    })
+    .with_children(|parent| {
+        parent.spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Px(450.0), Val::Px(480.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::ColumnReverse,
+                ..Default::default()
+            },
+            image: UiImage(asset_server.load("./sprites/ui/background.png")),
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            for line in [result.reason.get_str(), &winner] {
+                parent.spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        line,
+                        TextStyle { font: asset_server.load("./fonts/Symtext.ttf"), font_size: 30.0, color: Color::rgb(0.9, 0.9, 0.9) },
+                        Default::default()
+                    ),
+                    ..Default::default()
+                });
+            }
+
+            ButtonBuilder::build_button(
+                parent,
+                GameOverButton {
+                    button_type: GameOverButtonType::RematchButton,
+                },
+                &asset_server,
+            );
+
+            ButtonBuilder::build_button(
+                parent,
+                GameOverButton {
+                    button_type: GameOverButtonType::ExportButton,
+                },
+                &asset_server,
+            );
+
+            ButtonBuilder::build_button(
+                parent,
+                GameOverButton {
+                    button_type: GameOverButtonType::MainMenuButton,
+                },
+                &asset_server,
+            );
+        });
+    })
+    .insert(GameOverScreen);
+}
+
+fn handle_game_over_buttons(
+    mut interaction_query: Query<
+        (&Interaction, &mut UiColor, &GameOverButton),
+        (Changed<Interaction>, With<Button>)>,
+    mut app_state: ResMut<State<AppState>>,
+    mut main_menu_event: EventWriter<MainMenuEvent>,
+    game: Res<ChessGame>,
+    result: Res<GameResult>,
+    settings: Res<PgnSettings>,
+) {
+    for (interaction, mut color, button) in interaction_query.iter_mut() {
+        match interaction {
+            Interaction::Clicked => {
+                *color = UiColor(button_builder::PRESSED_BUTTON);
+                match button.button_type {
+                    GameOverButtonType::RematchButton => {
+                        // unwinds the finished game underneath and sets up a fresh one
+                        match app_state.replace(AppState::InGame) {
+                            Ok(_) => {},
+                            Err(msg) => {
+                                logger::log(msg);
+                            }
+                        }
+                    },
+                    GameOverButtonType::ExportButton => {
+                        pgn_export::export_pgn(&game, Some(&result), &settings);
+                    },
+                    GameOverButtonType::MainMenuButton => {
+                        main_menu_event.send(MainMenuEvent);
+                    }
+                }
+            },
+            Interaction::Hovered => {
+                *color = UiColor(button_builder::HOVERED_BUTTON);
+            },
+            Interaction::None => {
+                *color = UiColor(button_builder::NORMAL_BUTTON);
+            },
+        }
+    }
+}
+
+fn close_game_over(
+    mut commands: Commands,
+    screens: Query<Entity, With<GameOverScreen>>,
+) {
+    for screen in screens.iter() {
+        commands.entity(screen).despawn_recursive();
+    }
+}
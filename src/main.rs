@@ -1,7 +1,11 @@
 use bevy::prelude::*;
-use game::GamePlugin;
+use game::{GamePlugin, StartingPosition};
+use game::pgn_export::PgnSettings;
 use std::collections::HashMap;
+use std::time::Duration;
+use engine::search::SearchLimits;
 use main_menu::MainMenuPlugin;
+use game_over::GameOverPlugin;
 
 
 mod logger;
@@ -9,9 +13,62 @@ mod camera_controller;
 mod app_states;
 mod main_menu;
 mod game;
+mod game_over;
+mod engine;
 
 
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(index) = args.iter().position(|arg| arg == "--perft") {
+        let depth = args.get(index + 1).and_then(|depth| depth.parse().ok()).unwrap_or(1);
+        engine::perft::run(depth);
+        return;
+    }
+    if args.iter().any(|arg| arg == "--uci") {
+        engine::uci::run();
+        return;
+    }
+    if let Some(path) = args.iter().position(|arg| arg == "--epd").and_then(|index| args.get(index + 1)) {
+        let limits = SearchLimits {
+            depth: args.iter()
+                .position(|arg| arg == "--depth")
+                .and_then(|index| args.get(index + 1))
+                .and_then(|depth| depth.parse().ok()),
+            movetime: args.iter()
+                .position(|arg| arg == "--movetime")
+                .and_then(|index| args.get(index + 1))
+                .and_then(|millis| millis.parse().ok())
+                .map(Duration::from_millis),
+            infinite: false,
+        };
+        // a second a position unless told otherwise
+        let limits = if limits.depth.is_none() && limits.movetime.is_none() {
+            SearchLimits { movetime: Some(Duration::from_secs(1)), ..limits }
+        } else {
+            limits
+        };
+        engine::epd::run(path.as_ref(), limits);
+        return;
+    }
+    let starting_fen = args.iter()
+        .position(|arg| arg == "--fen")
+        .and_then(|index| args.get(index + 1).cloned());
+    let replayed_game = args.iter()
+        .position(|arg| arg == "--pgn")
+        .and_then(|index| args.get(index + 1))
+        .and_then(|path| {
+            let number = args.iter()
+                .position(|arg| arg == "--game")
+                .and_then(|index| args.get(index + 1))
+                .and_then(|number| number.parse().ok())
+                .unwrap_or(1);
+            game::replay::load_game(path.as_ref(), number)
+        });
+    let mut pgn_settings = PgnSettings::default();
+    if let Some(directory) = args.iter().position(|arg| arg == "--pgn-dir").and_then(|index| args.get(index + 1)) {
+        pgn_settings.directory = directory.into();
+    }
+
     App::new()
         .insert_resource(WindowDescriptor {
             title: "Sahister".to_string(),
@@ -20,9 +77,15 @@ fn main() {
             ..Default::default()
         })
         .insert_resource(ClearColor(Color::rgb(0.1, 0.1, 0.1)))
+        .insert_resource(StartingPosition {
+            fen: starting_fen,
+            pgn: replayed_game,
+        })
+        .insert_resource(pgn_settings)
         .add_plugins(DefaultPlugins)
         .add_plugin(MainMenuPlugin)
         .add_plugin(GamePlugin)
+        .add_plugin(GameOverPlugin)
         .add_startup_system_set(
             SystemSet::new()
                 .with_system(setup)
@@ -8,8 +8,8 @@ use button_events::{StartGameEvent, SetMenuEvent};
 use self::button_events::MainMenuEvent;
 
 mod set_menu;
-mod button_events;
-mod button_builder;
+pub mod button_events;
+pub mod button_builder;
 mod panel_builder;
 
 pub struct MainMenuPlugin;
@@ -204,7 +204,8 @@ fn handle_back_to_main_menu_transition(
     mut event_reader: EventReader<MainMenuEvent>,
 ) {
     if event_reader.iter().next().is_some() {
-        match app_state.set(AppState::MainMenu) {
+        // replace, so a paused game under the game over screen gets closed too
+        match app_state.replace(AppState::MainMenu) {
             Ok(_) => {},
             Err(msg) => {
                 logger::log(msg);